@@ -0,0 +1,44 @@
+use std::io::Write;
+
+use aws_sdk_bedrockruntime::Client;
+use futures::StreamExt;
+
+use stone_mason::{
+    anthropic::{AnthropicParamsBuilder, ClaudeTextModel},
+    completion::Conversation,
+    stream::StreamEvent,
+    StoneMason,
+};
+
+#[tokio::main]
+async fn main() {
+    let shared_config = aws_config::from_env().region("us-west-2").load().await;
+
+    let client = StoneMason::new(Client::new(&shared_config));
+
+    let model = ClaudeTextModel::ClaudeV2;
+
+    let prompt = "Write a short poem about Amazon Bedrock.";
+
+    let params = AnthropicParamsBuilder::default()
+        .conversation(&Conversation::new().user(prompt))
+        .unwrap()
+        .max_tokens_to_sample(500)
+        .build()
+        .unwrap();
+
+    let mut stream = client.invoke_stream(&model, &params).await.unwrap().boxed();
+
+    while let Some(event) = stream.next().await {
+        match event.unwrap() {
+            StreamEvent::Chunk(chunk) => {
+                print!("{}", chunk.completion);
+                std::io::stdout().flush().unwrap();
+            }
+            StreamEvent::End(end) => {
+                println!("\n\nstop reason: {:?}", end.stop_reason);
+                println!("metrics: {:?}", end.invocation_metrics);
+            }
+        }
+    }
+}
@@ -1,23 +1,37 @@
-use std::fmt::{Display, Error, Formatter};
+use crate::{BedrockModel, FromModelOutput};
 use derive_builder::Builder;
-use serde::Serialize;
-use crate::ModelVersion;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AI21LabsModel {
-    Jurassic2Mid(ModelVersion),
-    Jurassic2Ultra(ModelVersion),
+    Jurassic2MidV1,
+    Jurassic2UltraV1,
+}
+
+impl AI21LabsModel {
+    pub const ALL: [AI21LabsModel; 2] = [
+        AI21LabsModel::Jurassic2MidV1,
+        AI21LabsModel::Jurassic2UltraV1,
+    ];
 }
 
 impl Display for AI21LabsModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AI21LabsModel::Jurassic2Mid(v) if *v == ModelVersion::V1 => write!(f, "j2-mid-v1"),
-            AI21LabsModel::Jurassic2Ultra(v) if *v == ModelVersion::V1 => write!(f, "j2-ultra-v1"),
-            _ => Err(Error),
-        }
+        let id = match self {
+            AI21LabsModel::Jurassic2MidV1 => "j2-mid-v1",
+            AI21LabsModel::Jurassic2UltraV1 => "j2-ultra-v1",
+        };
+        write!(f, "{id}")
     }
 }
 
+impl BedrockModel for AI21LabsModel {
+    const PROVIDER: &'static str = "ai21";
+    type Params = AI21InferenceParameters;
+    type Response = AI21Response;
+}
+
 #[derive(Serialize, Builder, Clone, Debug)]
 #[builder(setter(strip_option))]
 pub struct AI21InferenceParameters {
@@ -73,7 +87,6 @@ pub struct AI21InferenceParameters {
     frequency_penalty: Option<FrequencyPenalty>,
 }
 
-
 #[derive(Serialize, Builder, Clone, Debug, Copy)]
 #[builder(setter(strip_option))]
 pub struct CountPenalty {
@@ -166,3 +179,143 @@ pub struct FrequencyPenalty {
     #[serde(rename(serialize = "applyToEmojis"))]
     apply_to_emojis: Option<bool>,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21Response {
+    pub id: AI21ResponseId,
+    pub prompt: AI21Prompt,
+    pub completions: Vec<AI21Completion>,
+}
+
+impl<'de> FromModelOutput<'de, AI21Response> for AI21Response {}
+
+/// Bedrock returns a numeric id, while the AI21 Studio API returns a UUID string.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AI21ResponseId {
+    Number(u64),
+    String(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21Prompt {
+    pub text: String,
+    pub tokens: Vec<AI21Token>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21Completion {
+    pub data: AI21CompletionData,
+
+    #[serde(rename = "finishReason")]
+    pub finish_reason: AI21FinishReason,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21CompletionData {
+    pub text: String,
+    pub tokens: Vec<AI21Token>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21Token {
+    #[serde(rename = "generatedToken")]
+    pub generated_token: AI21GeneratedToken,
+
+    /// Only populated when `topKReturn` is greater than zero.
+    #[serde(rename = "topTokens")]
+    pub top_tokens: Option<Vec<AI21TopToken>>,
+
+    #[serde(rename = "textRange")]
+    pub text_range: Option<AI21TextRange>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21GeneratedToken {
+    pub token: String,
+    pub logprob: f64,
+
+    #[serde(default)]
+    pub raw_logprob: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21TopToken {
+    pub token: String,
+    pub logprob: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AI21TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// `reason` is one of `endoftext`, `length` or `stop`. `length` is set when the completion
+/// hit `maxTokens`, and `sequence` when it ended on one of the `stopSequences`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AI21FinishReason {
+    pub reason: String,
+
+    #[serde(default)]
+    pub length: Option<u32>,
+
+    #[serde(default)]
+    pub sequence: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_deserialize_completion() {
+        let output = model_output(include_str!("../tests/fixtures/ai21/completion.json"));
+        let res = AI21Response::from_model_output(&output).unwrap();
+
+        assert_eq!(res.id, AI21ResponseId::Number(1234));
+        assert_eq!(res.prompt.text, "Tell me a joke");
+        assert_eq!(res.prompt.tokens.len(), 3);
+        assert_eq!(res.prompt.tokens[0].generated_token.token, "▁Tell▁me");
+        assert!(res.prompt.tokens[0].top_tokens.is_none());
+
+        assert_eq!(res.completions.len(), 1);
+        let completion = &res.completions[0];
+        assert!(completion.data.text.starts_with("\nWhy did the chicken"));
+        assert_eq!(completion.finish_reason.reason, "endoftext");
+        assert!(completion.finish_reason.length.is_none());
+
+        let token = &completion.data.tokens[0];
+        assert_eq!(token.generated_token.logprob, -0.012);
+        assert_eq!(token.generated_token.raw_logprob, Some(-0.012));
+        let range = token.text_range.unwrap();
+        assert_eq!((range.start, range.end), (0, 1));
+    }
+
+    #[test]
+    fn test_deserialize_top_tokens_and_finish_reasons() {
+        let output = model_output(include_str!("../tests/fixtures/ai21/top_tokens.json"));
+        let res = AI21Response::from_model_output(&output).unwrap();
+
+        assert_eq!(
+            res.id,
+            AI21ResponseId::String("c2f84b1e-3a7d-4c5e-9d41-2e8b5f0a6c13".to_string())
+        );
+
+        let top_tokens = res.completions[0].data.tokens[0]
+            .top_tokens
+            .as_ref()
+            .unwrap();
+        assert_eq!(top_tokens.len(), 2);
+        assert_eq!(top_tokens[0].token, "▁Paris");
+
+        let length = &res.completions[0].finish_reason;
+        assert_eq!(length.reason, "length");
+        assert_eq!(length.length, Some(2));
+
+        let stop = &res.completions[1].finish_reason;
+        assert_eq!(stop.reason, "stop");
+        assert_eq!(stop.sequence.as_deref(), Some("##"));
+    }
+}
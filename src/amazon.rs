@@ -1,28 +1,140 @@
-use crate::ModelVersion;
-use crate::ModelVersion::V1;
-use derive_builder::Builder;
-use serde::Serialize;
-use std::fmt::{Display, Error, Formatter};
+use crate::completion::{validate_turns, Conversation, Role};
+use crate::image::{self, Image};
+use crate::stream::{StreamChunk, StreamingModel};
+use crate::{BaseModel, BedrockModel, FromModelOutput};
+use crate::{Result, StoneMasonError};
+use derive_builder::{Builder, UninitializedFieldError};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AmazonModel {
-    TitanTextLite(ModelVersion),
-    TitanEmbeddingsText(ModelVersion),
-    TitanTextExpress(ModelVersion),
-    TitanTextAgile(ModelVersion),
+    TitanTextLiteV1,
+    TitanEmbeddingsTextV1,
+    TitanTextExpressV1,
+    TitanTextAgileV1,
+    TitanImageGeneratorV1,
+    TitanEmbeddingsTextV2,
+    TitanMultimodalEmbeddingsV1,
+}
+
+impl AmazonModel {
+    pub const ALL: [AmazonModel; 7] = [
+        AmazonModel::TitanTextLiteV1,
+        AmazonModel::TitanEmbeddingsTextV1,
+        AmazonModel::TitanTextExpressV1,
+        AmazonModel::TitanTextAgileV1,
+        AmazonModel::TitanImageGeneratorV1,
+        AmazonModel::TitanEmbeddingsTextV2,
+        AmazonModel::TitanMultimodalEmbeddingsV1,
+    ];
 }
 
 impl Display for AmazonModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AmazonModel::TitanTextLite(v) if *v == V1 => write!(f, "titan-text-lite-v1"),
-            AmazonModel::TitanEmbeddingsText(v) if *v == V1 => write!(f, "titan-embed-text-v1"),
-            AmazonModel::TitanTextExpress(v) if *v == V1 => write!(f, "titan-text-express-v1"),
-            AmazonModel::TitanTextAgile(v) if *v == V1 => write!(f, "titan-text-agile-v1"),
-            _ => Err(Error),
-        }
+        let id = match self {
+            AmazonModel::TitanTextLiteV1 => "titan-text-lite-v1",
+            AmazonModel::TitanEmbeddingsTextV1 => "titan-embed-text-v1",
+            AmazonModel::TitanTextExpressV1 => "titan-text-express-v1",
+            AmazonModel::TitanTextAgileV1 => "titan-text-agile-v1",
+            AmazonModel::TitanImageGeneratorV1 => "titan-image-generator-v1",
+            AmazonModel::TitanEmbeddingsTextV2 => "titan-embed-text-v2:0",
+            AmazonModel::TitanMultimodalEmbeddingsV1 => "titan-embed-image-v1",
+        };
+        write!(f, "{id}")
+    }
+}
+
+impl AmazonModel {
+    pub const PROVIDER: &'static str = "amazon";
+
+    /// Full Bedrock model id, e.g. `amazon.titan-text-express-v1`.
+    pub fn model_id(&self) -> String {
+        format!("{}.{self}", Self::PROVIDER)
+    }
+
+    /// The largest `maxTokenCount` the model accepts, `None` for the embeddings models.
+    pub fn max_token_count(&self) -> Option<u32> {
+        BaseModel::from(*self).capabilities().max_output_tokens
+    }
+}
+
+api_model! {
+    /// The Titan Text models, which take [`AmazonParams`].
+    pub enum TitanTextModel: AmazonModel, "AmazonParams" {
+        TitanTextLiteV1,
+        TitanTextExpressV1,
+        TitanTextAgileV1,
+    }
+}
+
+impl BedrockModel for TitanTextModel {
+    const PROVIDER: &'static str = AmazonModel::PROVIDER;
+    type Params = AmazonParams;
+    type Response = TitanTextResponse;
+
+    fn validate_params(&self, params: &AmazonParams) -> Result<()> {
+        Ok(params.text_generation_config.validate_for(self)?)
+    }
+}
+
+impl StreamingModel for TitanTextModel {
+    type Chunk = TitanTextStreamChunk;
+}
+
+api_model! {
+    /// Titan Embeddings G1 - Text, which takes [`TitanEmbeddingParams`].
+    pub enum TitanEmbeddingModel: AmazonModel, "TitanEmbeddingParams" {
+        TitanEmbeddingsTextV1,
+    }
+}
+
+impl BedrockModel for TitanEmbeddingModel {
+    const PROVIDER: &'static str = AmazonModel::PROVIDER;
+    type Params = TitanEmbeddingParams;
+    type Response = TitanEmbeddingResponse;
+}
+
+api_model! {
+    /// Titan Text Embeddings V2, which takes [`TitanEmbeddingV2Params`].
+    pub enum TitanEmbeddingV2Model: AmazonModel, "TitanEmbeddingV2Params" {
+        TitanEmbeddingsTextV2,
+    }
+}
+
+impl BedrockModel for TitanEmbeddingV2Model {
+    const PROVIDER: &'static str = AmazonModel::PROVIDER;
+    type Params = TitanEmbeddingV2Params;
+    type Response = TitanEmbeddingResponse;
+}
+
+api_model! {
+    /// Titan Multimodal Embeddings G1, which takes [`TitanMultimodalEmbeddingParams`].
+    pub enum TitanMultimodalEmbeddingModel: AmazonModel, "TitanMultimodalEmbeddingParams" {
+        TitanMultimodalEmbeddingsV1,
+    }
+}
+
+impl BedrockModel for TitanMultimodalEmbeddingModel {
+    const PROVIDER: &'static str = AmazonModel::PROVIDER;
+    type Params = TitanMultimodalEmbeddingParams;
+    type Response = TitanEmbeddingResponse;
+}
+
+api_model! {
+    /// Titan Image Generator G1, which takes [`TitanImageParams`].
+    pub enum TitanImageModel: AmazonModel, "TitanImageParams" {
+        TitanImageGeneratorV1,
     }
 }
 
+impl BedrockModel for TitanImageModel {
+    const PROVIDER: &'static str = AmazonModel::PROVIDER;
+    type Params = TitanImageParams;
+    type Response = TitanImageResponse;
+}
+
 #[derive(Serialize, Builder, Clone, Debug)]
 pub struct AmazonParams {
     #[serde(rename(serialize = "inputText"))]
@@ -32,18 +144,37 @@ pub struct AmazonParams {
     text_generation_config: TextGenerationConfig,
 }
 
+impl AmazonParamsBuilder {
+    /// Sets the input text to the rendered `conversation`.
+    pub fn conversation(&mut self, conversation: &Conversation) -> Result<&mut Self> {
+        Ok(self.input_text(render_prompt(conversation)?))
+    }
+}
+
+/// The largest `maxTokenCount` of any of the Titan Text models.
+const MAX_TOKEN_COUNT: u32 = 8192;
+
+/// Values are checked against the ranges Titan documents when built. Use
+/// [`TextGenerationConfig::validate_for`] to also check `maxTokenCount` against a particular
+/// model's limit, which [`crate::StoneMason::invoke`] does before sending a request.
 #[derive(Serialize, Builder, Clone, Debug)]
-#[builder(setter(strip_option))]
+#[builder(
+    setter(strip_option),
+    build_fn(validate = "Self::validate", error = "TitanConfigError")
+)]
 pub struct TextGenerationConfig {
+    /// Between 0 and 1.
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
 
+    /// Between 0 and 1.
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename(serialize = "topP"))]
     top_p: Option<f32>,
 
+    /// At most 4096 for Titan Text Lite, and 8192 for Titan Text Express.
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename(serialize = "maxTokenCount"))]
@@ -52,3 +183,909 @@ pub struct TextGenerationConfig {
     #[serde(rename(serialize = "stopSequences"))]
     stop_sequences: Vec<String>,
 }
+
+impl TextGenerationConfigBuilder {
+    fn validate(&self) -> Result<(), TitanConfigError> {
+        if let Some(Some(temperature)) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(TitanConfigError::Temperature(temperature));
+            }
+        }
+        if let Some(Some(top_p)) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(TitanConfigError::TopP(top_p));
+            }
+        }
+        match self.max_token_count {
+            Some(Some(count)) if count > MAX_TOKEN_COUNT => Err(TitanConfigError::MaxTokenCount {
+                count,
+                max: MAX_TOKEN_COUNT,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TextGenerationConfig {
+    /// Checks `maxTokenCount` against `model`'s limit.
+    pub fn validate_for(&self, model: &TitanTextModel) -> Result<(), TitanConfigError> {
+        match (
+            self.max_token_count,
+            AmazonModel::from(*model).max_token_count(),
+        ) {
+            (Some(count), Some(max)) if count > max => {
+                Err(TitanConfigError::MaxTokenCount { count, max })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TitanConfigError {
+    UninitializedField(&'static str),
+    Temperature(f32),
+    TopP(f32),
+    MaxTokenCount { count: u32, max: u32 },
+}
+
+impl Display for TitanConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TitanConfigError::UninitializedField(field) => {
+                write!(f, "`{field}` must be initialized")
+            }
+            TitanConfigError::Temperature(value) => {
+                write!(f, "temperature must be between 0 and 1, got {value}")
+            }
+            TitanConfigError::TopP(value) => write!(f, "topP must be between 0 and 1, got {value}"),
+            TitanConfigError::MaxTokenCount { count, max } => {
+                write!(f, "maxTokenCount must be at most {max}, got {count}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TitanConfigError {}
+
+impl From<UninitializedFieldError> for TitanConfigError {
+    fn from(e: UninitializedFieldError) -> Self {
+        TitanConfigError::UninitializedField(e.field_name())
+    }
+}
+
+/// Renders `conversation` into the `User: ...\nBot:` convention Titan Text expects, checking
+/// that the conversation starts with a user turn and alternates between user and assistant
+/// turns. Titan has no system role, so the system prompt is placed above the first `User:`
+/// turn, and a trailing assistant turn is left open for the model to continue from.
+///
+/// ```
+/// use stone_mason::amazon::render_prompt;
+/// use stone_mason::completion::Conversation;
+///
+/// let prompt = render_prompt(&Conversation::new().user("What is Amazon Bedrock?")).unwrap();
+///
+/// assert_eq!(prompt, "User: What is Amazon Bedrock?\nBot:");
+/// ```
+pub fn render_prompt(conversation: &Conversation) -> Result<String> {
+    let messages = conversation.messages();
+    validate_turns(messages)?;
+
+    let mut lines = Vec::with_capacity(messages.len() + 2);
+    if let Some(system) = conversation.system_prompt() {
+        lines.push(format!("{system}\n"));
+    }
+    for message in messages {
+        let role = match message.role {
+            Role::User => "User",
+            Role::Assistant => "Bot",
+        };
+        lines.push(format!("{role}: {}", message.content));
+    }
+    if messages.last().map(|m| m.role) == Some(Role::User) {
+        lines.push("Bot:".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+#[derive(Serialize, Builder, Clone, Debug)]
+pub struct TitanEmbeddingParams {
+    #[serde(rename(serialize = "inputText"))]
+    input_text: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TitanTextResponse {
+    #[serde(rename = "inputTextTokenCount")]
+    pub input_text_token_count: u32,
+
+    pub results: Vec<TitanTextResult>,
+}
+
+impl<'de> FromModelOutput<'de, TitanTextResponse> for TitanTextResponse {}
+
+/// `completion_reason` is one of `FINISH`, `LENGTH` or `CONTENT_FILTERED`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TitanTextResult {
+    #[serde(rename = "tokenCount")]
+    pub token_count: u32,
+
+    #[serde(rename = "outputText")]
+    pub output_text: String,
+
+    #[serde(rename = "completionReason")]
+    pub completion_reason: String,
+}
+
+/// The embedding lengths Titan Text Embeddings V2 can return.
+const EMBEDDING_V2_DIMENSIONS: [u32; 3] = [256, 512, 1024];
+
+/// The embedding lengths Titan Multimodal Embeddings can return.
+const MULTIMODAL_EMBEDDING_LENGTHS: [u32; 3] = [256, 384, 1024];
+
+/// Titan Text Embeddings V2 and Titan Multimodal Embeddings both return 1024-long embeddings
+/// unless asked otherwise.
+const DEFAULT_EMBEDDING_LENGTH: u32 = 1024;
+
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option), build_fn(validate = "Self::validate"))]
+#[serde(rename_all = "camelCase")]
+pub struct TitanEmbeddingV2Params {
+    #[builder(setter(into))]
+    input_text: String,
+
+    /// 256, 512 or 1024, the default.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+
+    /// Whether to scale the embedding to unit length, true by default.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalize: Option<bool>,
+}
+
+impl TitanEmbeddingV2ParamsBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match self.dimensions.flatten() {
+            Some(dimensions) if !EMBEDDING_V2_DIMENSIONS.contains(&dimensions) => Err(format!(
+                "dimensions must be one of {EMBEDDING_V2_DIMENSIONS:?}, got {dimensions}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TitanEmbeddingV2Params {
+    /// The length of the embedding this request returns.
+    pub fn dimensions(&self) -> u32 {
+        self.dimensions.unwrap_or(DEFAULT_EMBEDDING_LENGTH)
+    }
+}
+
+/// Embeds text, an image or both into the same space, so text can be used to search images.
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option), build_fn(validate = "Self::validate"))]
+#[serde(rename_all = "camelCase")]
+pub struct TitanMultimodalEmbeddingParams {
+    #[builder(default = "None", setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_text: Option<String>,
+
+    #[builder(default = "None", setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_image: Option<Image>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_config: Option<EmbeddingConfig>,
+}
+
+impl TitanMultimodalEmbeddingParamsBuilder {
+    fn validate(&self) -> Result<(), String> {
+        if !matches!(self.input_text, Some(Some(_))) && !matches!(self.input_image, Some(Some(_))) {
+            return Err("at least one of input_text and input_image is required".to_string());
+        }
+        match &self.embedding_config {
+            Some(Some(EmbeddingConfig {
+                output_embedding_length: length,
+            })) if !MULTIMODAL_EMBEDDING_LENGTHS.contains(length) => Err(format!(
+                "outputEmbeddingLength must be one of {MULTIMODAL_EMBEDDING_LENGTHS:?}, got {length}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TitanMultimodalEmbeddingParams {
+    /// The length of the embedding this request returns.
+    pub fn output_embedding_length(&self) -> u32 {
+        self.embedding_config
+            .map_or(DEFAULT_EMBEDDING_LENGTH, |config| {
+                config.output_embedding_length
+            })
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingConfig {
+    /// 256, 384 or 1024, the default.
+    pub output_embedding_length: u32,
+}
+
+/// Returned by every Titan embeddings model.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TitanEmbeddingResponse {
+    pub embedding: Vec<f32>,
+
+    /// Zero when only an image was embedded.
+    #[serde(rename = "inputTextTokenCount", default)]
+    pub input_text_token_count: u32,
+}
+
+impl<'de> FromModelOutput<'de, TitanEmbeddingResponse> for TitanEmbeddingResponse {}
+
+impl TitanEmbeddingResponse {
+    /// The embedding, checked to be `dimension` long, the length the request asked for.
+    pub fn into_embedding(self, dimension: usize) -> Result<Vec<f32>> {
+        if self.embedding.len() != dimension {
+            return Err(StoneMasonError::UnexpectedResponse(format!(
+                "expected an embedding of length {dimension}, got {}",
+                self.embedding.len()
+            )));
+        }
+        Ok(self.embedding)
+    }
+}
+
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option))]
+pub struct TitanImageParams {
+    /// Serialized as `taskType` along with the params of the task.
+    #[serde(flatten)]
+    task: TitanImageTask,
+
+    #[builder(default = "None")]
+    #[serde(
+        rename(serialize = "imageGenerationConfig"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    image_generation_config: Option<ImageGenerationConfig>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "taskType", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TitanImageTask {
+    TextImage {
+        #[serde(rename = "textToImageParams")]
+        params: TextToImageParams,
+    },
+    Inpainting {
+        #[serde(rename = "inPaintingParams")]
+        params: InPaintingParams,
+    },
+    Outpainting {
+        #[serde(rename = "outPaintingParams")]
+        params: OutPaintingParams,
+    },
+    ImageVariation {
+        #[serde(rename = "imageVariationParams")]
+        params: ImageVariationParams,
+    },
+}
+
+impl From<TextToImageParams> for TitanImageTask {
+    fn from(params: TextToImageParams) -> Self {
+        TitanImageTask::TextImage { params }
+    }
+}
+
+impl From<InPaintingParams> for TitanImageTask {
+    fn from(params: InPaintingParams) -> Self {
+        TitanImageTask::Inpainting { params }
+    }
+}
+
+impl From<OutPaintingParams> for TitanImageTask {
+    fn from(params: OutPaintingParams) -> Self {
+        TitanImageTask::Outpainting { params }
+    }
+}
+
+impl From<ImageVariationParams> for TitanImageTask {
+    fn from(params: ImageVariationParams) -> Self {
+        TitanImageTask::ImageVariation { params }
+    }
+}
+
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option, into))]
+#[serde(rename_all = "camelCase")]
+pub struct TextToImageParams {
+    text: String,
+
+    /// What not to include in the image.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_text: Option<String>,
+}
+
+/// Replaces the masked area of `image`, with what `text` describes or with the background if
+/// there is no `text`.
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option, into))]
+#[serde(rename_all = "camelCase")]
+pub struct InPaintingParams {
+    image: Image,
+
+    #[serde(flatten)]
+    mask: Mask,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_text: Option<String>,
+}
+
+/// Replaces everything outside the masked area of `image` with what `text` describes.
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option, into))]
+#[serde(rename_all = "camelCase")]
+pub struct OutPaintingParams {
+    image: Image,
+
+    #[serde(flatten)]
+    mask: Mask,
+
+    text: String,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_text: Option<String>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    out_painting_mode: Option<OutPaintingMode>,
+}
+
+/// The area of an image to edit, given either as a description of what is in it or as a
+/// black and white image the size of the original, with the area to edit in black.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Mask {
+    MaskPrompt(String),
+    MaskImage(Image),
+}
+
+/// With `Precise`, the masked area is kept exactly as is; with `Default`, it may be blended
+/// into the new surroundings.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OutPaintingMode {
+    Default,
+    Precise,
+}
+
+/// Generates variations of `images`, guided by `text` if given.
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option, into))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVariationParams {
+    images: Vec<Image>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_text: Option<String>,
+}
+
+/// Values are checked against the ranges Titan Image Generator documents when built.
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option), build_fn(validate = "Self::validate"))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageGenerationConfig {
+    /// Between 1 and 5.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    number_of_images: Option<u32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<ImageQuality>,
+
+    /// Set together with `width`, e.g. 1024x1024 or 1280x768.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+
+    /// How closely the image follows the prompt, between 1.1 and 10.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cfg_scale: Option<f32>,
+
+    /// Between 0 and 2147483646.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+}
+
+const MAX_IMAGE_SEED: u32 = 2_147_483_646;
+
+impl ImageGenerationConfigBuilder {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(Some(count)) = self.number_of_images {
+            if !(1..=5).contains(&count) {
+                return Err(format!(
+                    "numberOfImages must be between 1 and 5, got {count}"
+                ));
+            }
+        }
+        if let Some(Some(cfg_scale)) = self.cfg_scale {
+            if !(1.1..=10.0).contains(&cfg_scale) {
+                return Err(format!(
+                    "cfgScale must be between 1.1 and 10, got {cfg_scale}"
+                ));
+            }
+        }
+        if let Some(Some(seed)) = self.seed {
+            if seed > MAX_IMAGE_SEED {
+                return Err(format!("seed must be at most {MAX_IMAGE_SEED}, got {seed}"));
+            }
+        }
+        match (self.height.flatten(), self.width.flatten()) {
+            (Some(_), None) | (None, Some(_)) => {
+                Err("height and width must be set together".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageQuality {
+    Standard,
+    Premium,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TitanImageResponse {
+    /// The generated PNG images, decoded from base64. Empty when generation failed.
+    #[serde(default)]
+    pub images: Vec<Image>,
+
+    /// Set when generation failed, e.g. because the prompt was blocked.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl<'de> FromModelOutput<'de, TitanImageResponse> for TitanImageResponse {}
+
+impl TitanImageResponse {
+    /// Writes each image into `dir` as `{index}.png`, returning the paths written.
+    pub fn save_images(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let images = self
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| (format!("{i}.png"), image.as_bytes()));
+        image::save_all(dir.as_ref(), images)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TitanTextStreamChunk {
+    pub output_text: String,
+    pub index: u32,
+    pub total_output_text_token_count: Option<u32>,
+    pub completion_reason: Option<String>,
+
+    /// Only populated on the first chunk.
+    pub input_text_token_count: Option<u32>,
+}
+
+impl StreamChunk for TitanTextStreamChunk {
+    fn stop_reason(&self) -> Option<&str> {
+        self.completion_reason.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_render_conversation() {
+        let prompt = render_prompt(
+            &Conversation::new()
+                .system("You are a helpful assistant.")
+                .user("Hi")
+                .assistant("Hello! How can I help?")
+                .user("Tell me a joke"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            prompt,
+            "You are a helpful assistant.\n\nUser: Hi\nBot: Hello! How can I help?\nUser: Tell me a joke\nBot:"
+        );
+
+        let prompt = render_prompt(&Conversation::new().user("Hi").assistant("Hello")).unwrap();
+        assert_eq!(prompt, "User: Hi\nBot: Hello");
+
+        assert!(render_prompt(&Conversation::new().assistant("Hello")).is_err());
+    }
+
+    #[test]
+    fn test_config_ranges() {
+        let err = TextGenerationConfigBuilder::default()
+            .temperature(1.5)
+            .stop_sequences(vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(err, TitanConfigError::Temperature(1.5));
+
+        let err = TextGenerationConfigBuilder::default()
+            .top_p(-0.1)
+            .stop_sequences(vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(err, TitanConfigError::TopP(-0.1));
+
+        let err = TextGenerationConfigBuilder::default()
+            .max_token_count(10_000)
+            .stop_sequences(vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            TitanConfigError::MaxTokenCount {
+                count: 10_000,
+                max: 8192
+            }
+        );
+
+        let err = TextGenerationConfigBuilder::default().build().unwrap_err();
+        assert_eq!(err, TitanConfigError::UninitializedField("stop_sequences"));
+    }
+
+    #[test]
+    fn test_config_model_limits() {
+        let config = TextGenerationConfigBuilder::default()
+            .max_token_count(6000)
+            .stop_sequences(vec![])
+            .build()
+            .unwrap();
+
+        assert!(config
+            .validate_for(&TitanTextModel::TitanTextExpressV1)
+            .is_ok());
+        assert_eq!(
+            config.validate_for(&TitanTextModel::TitanTextLiteV1),
+            Err(TitanConfigError::MaxTokenCount {
+                count: 6000,
+                max: 4096
+            })
+        );
+
+        let params = AmazonParamsBuilder::default()
+            .conversation(&Conversation::new().user("Hi"))
+            .unwrap()
+            .text_generation_config(config)
+            .build()
+            .unwrap();
+        assert!(TitanTextModel::TitanTextLiteV1
+            .validate_params(&params)
+            .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_text_response() {
+        let output = model_output(include_str!("../tests/fixtures/amazon/titan_text.json"));
+        let res = TitanTextResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.input_text_token_count, 11);
+        assert_eq!(res.results.len(), 1);
+        assert_eq!(res.results[0].token_count, 36);
+        assert!(res.results[0].output_text.starts_with("\nAmazon Bedrock"));
+        assert_eq!(res.results[0].completion_reason, "FINISH");
+    }
+
+    #[test]
+    fn test_deserialize_embedding_response() {
+        let output = model_output(include_str!(
+            "../tests/fixtures/amazon/titan_embedding.json"
+        ));
+        let res = TitanEmbeddingResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.input_text_token_count, 4);
+        assert_eq!(
+            res.embedding,
+            vec![0.4453125, -0.22558594, 0.016235352, -0.0078125]
+        );
+    }
+
+    #[test]
+    fn test_api_models_partition_amazon_models() {
+        let models: Vec<AmazonModel> = TitanTextModel::ALL
+            .into_iter()
+            .map(AmazonModel::from)
+            .chain(TitanEmbeddingModel::ALL.into_iter().map(AmazonModel::from))
+            .chain(
+                TitanEmbeddingV2Model::ALL
+                    .into_iter()
+                    .map(AmazonModel::from),
+            )
+            .chain(
+                TitanMultimodalEmbeddingModel::ALL
+                    .into_iter()
+                    .map(AmazonModel::from),
+            )
+            .chain(TitanImageModel::ALL.into_iter().map(AmazonModel::from))
+            .collect();
+        assert_eq!(models.len(), AmazonModel::ALL.len());
+        for model in AmazonModel::ALL {
+            assert!(models.contains(&model), "{model}");
+        }
+
+        let model = TitanTextModel::TitanTextExpressV1;
+        assert_eq!(model.model_id(), "amazon.titan-text-express-v1");
+        assert_eq!(
+            "amazon.titan-text-express-v1"
+                .parse::<TitanTextModel>()
+                .unwrap(),
+            model
+        );
+        assert_eq!(
+            TitanTextModel::try_from(AmazonModel::TitanEmbeddingsTextV1)
+                .unwrap_err()
+                .to_string(),
+            "amazon.titan-embed-text-v1 does not support AmazonParams"
+        );
+    }
+
+    #[test]
+    fn test_serialize_text_to_image_params() {
+        let params = TitanImageParamsBuilder::default()
+            .task(
+                TextToImageParamsBuilder::default()
+                    .text("a lighthouse at dusk")
+                    .negative_text("people")
+                    .build()
+                    .unwrap()
+                    .into(),
+            )
+            .image_generation_config(
+                ImageGenerationConfigBuilder::default()
+                    .number_of_images(2)
+                    .quality(ImageQuality::Premium)
+                    .height(768)
+                    .width(1280)
+                    .cfg_scale(8.0)
+                    .seed(42)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "taskType": "TEXT_IMAGE",
+                "textToImageParams": {"text": "a lighthouse at dusk", "negativeText": "people"},
+                "imageGenerationConfig": {
+                    "numberOfImages": 2,
+                    "quality": "premium",
+                    "height": 768,
+                    "width": 1280,
+                    "cfgScale": 8.0,
+                    "seed": 42,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_editing_params() {
+        let task: TitanImageTask = InPaintingParamsBuilder::default()
+            .image(Image(b"init".to_vec()))
+            .mask(Mask::MaskPrompt("the car".to_string()))
+            .build()
+            .unwrap()
+            .into();
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "taskType": "INPAINTING",
+                "inPaintingParams": {"image": "aW5pdA==", "maskPrompt": "the car"},
+            })
+        );
+
+        let task: TitanImageTask = OutPaintingParamsBuilder::default()
+            .image(Image(b"init".to_vec()))
+            .mask(Mask::MaskImage(Image(b"mask".to_vec())))
+            .text("a beach")
+            .out_painting_mode(OutPaintingMode::Precise)
+            .build()
+            .unwrap()
+            .into();
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "taskType": "OUTPAINTING",
+                "outPaintingParams": {
+                    "image": "aW5pdA==",
+                    "maskImage": "bWFzaw==",
+                    "text": "a beach",
+                    "outPaintingMode": "PRECISE",
+                },
+            })
+        );
+
+        let task: TitanImageTask = ImageVariationParamsBuilder::default()
+            .images(vec![Image(b"init".to_vec())])
+            .build()
+            .unwrap()
+            .into();
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "taskType": "IMAGE_VARIATION",
+                "imageVariationParams": {"images": ["aW5pdA=="]},
+            })
+        );
+    }
+
+    #[test]
+    fn test_image_config_ranges() {
+        let err = ImageGenerationConfigBuilder::default()
+            .number_of_images(6)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "numberOfImages must be between 1 and 5, got 6"
+        );
+
+        let err = ImageGenerationConfigBuilder::default()
+            .height(1024)
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "height and width must be set together");
+    }
+
+    #[test]
+    fn test_deserialize_image_response() {
+        let output = model_output(include_str!("../tests/fixtures/amazon/titan_image.json"));
+        let res = TitanImageResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.images.len(), 2);
+        assert!(res.images[0].as_bytes().starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(res.error, None);
+
+        let dir = std::env::temp_dir().join(format!("stone-mason-titan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = res.save_images(&dir).unwrap();
+        assert_eq!(paths, vec![dir.join("0.png"), dir.join("1.png")]);
+        assert_eq!(std::fs::read(&paths[1]).unwrap(), res.images[1].as_bytes());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_deserialize_image_error_response() {
+        let output =
+            model_output(r#"{"error":"This request has been blocked by our content filters."}"#);
+        let res = TitanImageResponse::from_model_output(&output).unwrap();
+
+        assert!(res.images.is_empty());
+        assert_eq!(
+            res.error.as_deref(),
+            Some("This request has been blocked by our content filters.")
+        );
+    }
+
+    #[test]
+    fn test_serialize_embedding_v2_params() {
+        let params = TitanEmbeddingV2ParamsBuilder::default()
+            .input_text("Hello world")
+            .dimensions(256)
+            .normalize(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"inputText":"Hello world","dimensions":256,"normalize":true}"#
+        );
+
+        let err = TitanEmbeddingV2ParamsBuilder::default()
+            .input_text("Hello world")
+            .dimensions(384)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "dimensions must be one of [256, 512, 1024], got 384"
+        );
+    }
+
+    #[test]
+    fn test_serialize_multimodal_embedding_params() {
+        let params = TitanMultimodalEmbeddingParamsBuilder::default()
+            .input_text("a red shoe")
+            .input_image(Image(b"shoe".to_vec()))
+            .embedding_config(EmbeddingConfig {
+                output_embedding_length: 384,
+            })
+            .build()
+            .unwrap();
+        assert_eq!(params.output_embedding_length(), 384);
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"inputText":"a red shoe","inputImage":"c2hvZQ==","embeddingConfig":{"outputEmbeddingLength":384}}"#
+        );
+
+        let err = TitanMultimodalEmbeddingParamsBuilder::default()
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at least one of input_text and input_image is required"
+        );
+
+        let err = TitanMultimodalEmbeddingParamsBuilder::default()
+            .input_text("a red shoe")
+            .embedding_config(EmbeddingConfig {
+                output_embedding_length: 512,
+            })
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "outputEmbeddingLength must be one of [256, 384, 1024], got 512"
+        );
+    }
+
+    #[test]
+    fn test_embedding_dimension_check() {
+        let output = model_output(include_str!(
+            "../tests/fixtures/amazon/titan_multimodal_embedding.json"
+        ));
+        let res = TitanEmbeddingResponse::from_model_output(&output).unwrap();
+        assert_eq!(res.input_text_token_count, 0);
+        assert_eq!(res.clone().into_embedding(4).unwrap().len(), 4);
+
+        let err = res.into_embedding(1024).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected response: expected an embedding of length 1024, got 4"
+        );
+    }
+
+    #[test]
+    fn test_serialize_embedding_params() {
+        let params = TitanEmbeddingParamsBuilder::default()
+            .input_text("Hello world".to_string())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"inputText":"Hello world"}"#
+        );
+    }
+}
@@ -1,26 +1,103 @@
-use crate::ModelVersion::{V1, V2};
-use crate::{FromModelOutput, ModelVersion};
+pub mod messages;
+
+use crate::completion::{validate_turns, Conversation, Role};
+use crate::stream::{StreamChunk, StreamingModel};
+use crate::{BedrockModel, FromModelOutput};
+use crate::{Result, StoneMasonError};
 use derive_builder::Builder;
+use messages::{MessagesParams, MessagesResponse, MessagesStreamEvent};
 use serde::{Deserialize, Serialize};
-use std::fmt::{Display, Error, Formatter};
+use std::fmt::{Display, Formatter};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AnthropicModel {
-    Claude(ModelVersion),
-    ClaudeInstant(ModelVersion),
+    ClaudeV1,
+    ClaudeV2,
+    ClaudeV2_1,
+    ClaudeInstantV1,
+    Claude3HaikuV1,
+    Claude3SonnetV1,
+    Claude3OpusV1,
+}
+
+impl AnthropicModel {
+    pub const ALL: [AnthropicModel; 7] = [
+        AnthropicModel::ClaudeV1,
+        AnthropicModel::ClaudeV2,
+        AnthropicModel::ClaudeV2_1,
+        AnthropicModel::ClaudeInstantV1,
+        AnthropicModel::Claude3HaikuV1,
+        AnthropicModel::Claude3SonnetV1,
+        AnthropicModel::Claude3OpusV1,
+    ];
+
+    pub const PROVIDER: &'static str = "anthropic";
+
+    /// Full Bedrock model id, e.g. `anthropic.claude-v2`.
+    pub fn model_id(&self) -> String {
+        format!("{}.{self}", Self::PROVIDER)
+    }
 }
 
 impl Display for AnthropicModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AnthropicModel::Claude(v) if *v == V1 => write!(f, "claude-v1"),
-            AnthropicModel::Claude(v) if *v == V2 => write!(f, "claude-v2"),
-            AnthropicModel::ClaudeInstant(v) if *v == V1 => write!(f, "claude-instant-v1"),
-            _ => Err(Error),
-        }
+        let id = match self {
+            AnthropicModel::ClaudeV1 => "claude-v1",
+            AnthropicModel::ClaudeV2 => "claude-v2",
+            AnthropicModel::ClaudeV2_1 => "claude-v2:1",
+            AnthropicModel::ClaudeInstantV1 => "claude-instant-v1",
+            AnthropicModel::Claude3HaikuV1 => "claude-3-haiku-20240307-v1:0",
+            AnthropicModel::Claude3SonnetV1 => "claude-3-sonnet-20240229-v1:0",
+            AnthropicModel::Claude3OpusV1 => "claude-3-opus-20240229-v1:0",
+        };
+        write!(f, "{id}")
+    }
+}
+
+api_model! {
+    /// The models before Claude 3, which take a `\n\nHuman:`/`\n\nAssistant:` prompt in
+    /// [`AnthropicParams`].
+    pub enum ClaudeTextModel: AnthropicModel, "AnthropicParams" {
+        ClaudeV1,
+        ClaudeV2,
+        ClaudeV2_1,
+        ClaudeInstantV1,
+    }
+}
+
+impl BedrockModel for ClaudeTextModel {
+    const PROVIDER: &'static str = AnthropicModel::PROVIDER;
+    type Params = AnthropicParams;
+    type Response = AnthropicResponse;
+}
+
+impl StreamingModel for ClaudeTextModel {
+    type Chunk = AnthropicStreamChunk;
+}
+
+api_model! {
+    /// The models which take [`MessagesParams`], i.e. every model from Claude Instant 1.2 on.
+    pub enum ClaudeMessagesModel: AnthropicModel, "MessagesParams" {
+        ClaudeV2,
+        ClaudeV2_1,
+        ClaudeInstantV1,
+        Claude3HaikuV1,
+        Claude3SonnetV1,
+        Claude3OpusV1,
     }
 }
 
-#[derive(Serialize, Builder)]
+impl BedrockModel for ClaudeMessagesModel {
+    const PROVIDER: &'static str = AnthropicModel::PROVIDER;
+    type Params = MessagesParams;
+    type Response = MessagesResponse;
+}
+
+impl StreamingModel for ClaudeMessagesModel {
+    type Chunk = MessagesStreamEvent;
+}
+
+#[derive(Serialize, Builder, Clone, Debug)]
 #[builder(setter(strip_option))]
 pub struct AnthropicParams {
     prompt: String,
@@ -43,11 +120,223 @@ pub struct AnthropicParams {
     top_k: Option<u32>,
 }
 
-#[derive(Deserialize, Debug)]
+impl AnthropicParamsBuilder {
+    /// Sets the prompt to the rendered `conversation`.
+    pub fn conversation(&mut self, conversation: &Conversation) -> Result<&mut Self> {
+        Ok(self.prompt(render_prompt(conversation)?))
+    }
+}
+
+const HUMAN: &str = "\n\nHuman:";
+const ASSISTANT: &str = "\n\nAssistant:";
+
+/// Renders `conversation` into the `\n\nHuman:`/`\n\nAssistant:` prompt format the text
+/// completions API requires. The system prompt is placed before the first `\n\nHuman:` turn,
+/// and a trailing assistant turn is rendered as a prefill which Claude continues from.
+///
+/// Checks that the conversation starts with a user turn, alternates between user and
+/// assistant turns, and that no turn contains the turn markers itself.
+///
+/// ```
+/// use stone_mason::anthropic::render_prompt;
+/// use stone_mason::completion::Conversation;
+///
+/// let prompt = render_prompt(
+///     &Conversation::new()
+///         .system("You are a pirate.")
+///         .user("What is Amazon Bedrock?")
+///         .assistant("Arr,"),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     prompt,
+///     "You are a pirate.\n\nHuman: What is Amazon Bedrock?\n\nAssistant: Arr,"
+/// );
+/// ```
+pub fn render_prompt(conversation: &Conversation) -> Result<String> {
+    validate_turns(conversation.messages())?;
+
+    let texts = conversation
+        .system_prompt()
+        .into_iter()
+        .chain(conversation.messages().iter().map(|m| m.content.as_str()));
+    if texts
+        .into_iter()
+        .any(|text| text.contains(HUMAN) || text.contains(ASSISTANT))
+    {
+        return Err(StoneMasonError::InvalidConversation(format!(
+            "conversation turns cannot contain {HUMAN:?} or {ASSISTANT:?}"
+        )));
+    }
+
+    let mut prompt = conversation.system_prompt().unwrap_or_default().to_string();
+    for message in conversation.messages() {
+        let marker = match message.role {
+            Role::User => HUMAN,
+            Role::Assistant => ASSISTANT,
+        };
+        prompt.push_str(marker);
+        if !message.content.is_empty() {
+            prompt.push(' ');
+            prompt.push_str(&message.content);
+        }
+    }
+
+    match conversation.messages().last() {
+        Some(prefill) if prefill.role == Role::Assistant => {
+            if prefill.content.ends_with(char::is_whitespace) {
+                return Err(StoneMasonError::InvalidConversation(
+                    "assistant prefill cannot end with whitespace".to_string(),
+                ));
+            }
+        }
+        _ => prompt.push_str(ASSISTANT),
+    }
+
+    Ok(prompt)
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct AnthropicResponse {
     pub completion: String,
     pub stop_reason: String,
-    pub stop: String,
+
+    /// The stop sequence which ended the completion, `None` when it hit `max_tokens_to_sample`.
+    pub stop: Option<String>,
 }
 
 impl<'de> FromModelOutput<'de, AnthropicResponse> for AnthropicResponse {}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnthropicStreamChunk {
+    pub completion: String,
+    pub stop_reason: Option<String>,
+    pub stop: Option<String>,
+}
+
+impl StreamChunk for AnthropicStreamChunk {
+    fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_single_turn() {
+        let prompt = render_prompt(&Conversation::new().user("Hello")).unwrap();
+        assert_eq!(prompt, "\n\nHuman: Hello\n\nAssistant:");
+    }
+
+    #[test]
+    fn test_render_multi_turn_with_system() {
+        let prompt = render_prompt(
+            &Conversation::new()
+                .system("You are a helpful assistant.")
+                .user("Hi")
+                .assistant("Hello! How can I help?")
+                .user("Tell me a joke"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            prompt,
+            "You are a helpful assistant.\n\nHuman: Hi\n\nAssistant: Hello! How can I help?\n\nHuman: Tell me a joke\n\nAssistant:"
+        );
+    }
+
+    #[test]
+    fn test_render_prefill() {
+        let prompt = render_prompt(
+            &Conversation::new()
+                .user("List three colors as JSON")
+                .assistant("{"),
+        )
+        .unwrap();
+        assert_eq!(
+            prompt,
+            "\n\nHuman: List three colors as JSON\n\nAssistant: {"
+        );
+
+        let err = render_prompt(
+            &Conversation::new()
+                .user("List three colors as JSON")
+                .assistant("{ "),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "assistant prefill cannot end with whitespace"
+        );
+    }
+
+    #[test]
+    fn test_render_rejects_invalid_conversations() {
+        let err = render_prompt(&Conversation::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "conversation must contain at least one user turn"
+        );
+
+        let err = render_prompt(&Conversation::new().assistant("Hi")).unwrap_err();
+        assert_eq!(err.to_string(), "conversation must start with a user turn");
+
+        let err = render_prompt(&Conversation::new().user("Hi").user("Hello?")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "conversation turns must alternate, found two User turns in a row"
+        );
+
+        let err = render_prompt(&Conversation::new().user("Hi\n\nAssistant: Sure")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("conversation turns cannot contain"));
+    }
+
+    #[test]
+    fn test_params_from_conversation() {
+        let params = AnthropicParamsBuilder::default()
+            .conversation(&Conversation::new().user("Hello"))
+            .unwrap()
+            .max_tokens_to_sample(100)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"prompt":"\n\nHuman: Hello\n\nAssistant:","max_tokens_to_sample":100}"#
+        );
+    }
+
+    #[test]
+    fn test_api_models() {
+        assert_eq!(
+            ClaudeMessagesModel::try_from(AnthropicModel::Claude3SonnetV1).unwrap(),
+            ClaudeMessagesModel::Claude3SonnetV1
+        );
+        assert_eq!(
+            ClaudeTextModel::try_from(AnthropicModel::Claude3SonnetV1)
+                .unwrap_err()
+                .to_string(),
+            "anthropic.claude-3-sonnet-20240229-v1:0 does not support AnthropicParams"
+        );
+        assert!(ClaudeMessagesModel::try_from(AnthropicModel::ClaudeV1).is_err());
+        assert_eq!(
+            ClaudeTextModel::ClaudeV2_1.model_id(),
+            "anthropic.claude-v2:1"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_completion_response() {
+        let output = crate::tests::model_output(
+            r#"{"completion":" Hello","stop_reason":"stop_sequence","stop":"\n\nHuman:"}"#,
+        );
+        let res = AnthropicResponse::from_model_output(&output).unwrap();
+        assert_eq!(res.completion, " Hello");
+        assert_eq!(res.stop.as_deref(), Some("\n\nHuman:"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Request and response bodies of the Messages API, which Claude Instant, Claude 2 and the
+//! Claude 3 models accept instead of a `\n\nHuman:`/`\n\nAssistant:` prompt.
+
+use crate::completion::{validate_turns, Conversation, Role};
+use crate::image::Image;
+use crate::stream::StreamChunk;
+use crate::{FromModelOutput, Result};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+/// The only `anthropic_version` Bedrock accepts.
+pub const ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option))]
+pub struct MessagesParams {
+    #[builder(default = "ANTHROPIC_VERSION.to_string()", setter(into))]
+    anthropic_version: String,
+
+    max_tokens: u32,
+
+    messages: Vec<AnthropicMessage>,
+
+    #[builder(default = "None", setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+impl MessagesParamsBuilder {
+    /// Sets the system prompt and messages from `conversation`, as text content blocks.
+    pub fn conversation(&mut self, conversation: &Conversation) -> Result<&mut Self> {
+        validate_turns(conversation.messages())?;
+        if let Some(system) = conversation.system_prompt() {
+            self.system(system);
+        }
+        Ok(self.messages(
+            conversation
+                .messages()
+                .iter()
+                .map(|message| AnthropicMessage {
+                    role: message.role,
+                    content: vec![ContentBlock::text(&message.content)],
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnthropicMessage {
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+}
+
+impl AnthropicMessage {
+    pub fn user(content: Vec<ContentBlock>) -> Self {
+        AnthropicMessage {
+            role: Role::User,
+            content,
+        }
+    }
+
+    pub fn assistant(content: Vec<ContentBlock>) -> Self {
+        AnthropicMessage {
+            role: Role::Assistant,
+            content,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text { text: String },
+    Image { source: ImageSource },
+}
+
+impl ContentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    pub fn image(media_type: MediaType, image: impl Into<Image>) -> Self {
+        ContentBlock::Image {
+            source: ImageSource::Base64 {
+                media_type,
+                data: image.into(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImageSource {
+    Base64 { media_type: MediaType, data: Image },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaType {
+    #[serde(rename = "image/jpeg")]
+    Jpeg,
+    #[serde(rename = "image/png")]
+    Png,
+    #[serde(rename = "image/gif")]
+    Gif,
+    #[serde(rename = "image/webp")]
+    Webp,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MessagesResponse {
+    pub id: String,
+    pub model: String,
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+
+    /// `end_turn`, `max_tokens` or `stop_sequence`, only `None` in [`MessagesStreamEvent::MessageStart`].
+    pub stop_reason: Option<String>,
+
+    /// The stop sequence which ended the message, if any.
+    pub stop_sequence: Option<String>,
+
+    pub usage: MessagesUsage,
+}
+
+impl MessagesResponse {
+    /// The text of every text content block, concatenated.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                ContentBlock::Image { .. } => None,
+            })
+            .collect()
+    }
+}
+
+impl<'de> FromModelOutput<'de, MessagesResponse> for MessagesResponse {}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessagesUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// A server-sent event of a streamed Messages API response.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessagesStreamEvent {
+    /// The message, with no content yet.
+    MessageStart {
+        message: MessagesResponse,
+    },
+
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+
+    /// Text to append to the content block at `index`.
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentDelta,
+    },
+
+    ContentBlockStop {
+        index: usize,
+    },
+
+    /// Sent once generation stops, with the final output token count.
+    MessageDelta {
+        delta: MessageDelta,
+        usage: MessageDeltaUsage,
+    },
+
+    MessageStop,
+
+    Ping,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta { text: String },
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MessageDelta {
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageDeltaUsage {
+    pub output_tokens: u32,
+}
+
+impl MessagesStreamEvent {
+    /// The text added by this event, if any.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            MessagesStreamEvent::ContentBlockDelta {
+                delta: ContentDelta::TextDelta { text },
+                ..
+            } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl StreamChunk for MessagesStreamEvent {
+    fn stop_reason(&self) -> Option<&str> {
+        match self {
+            MessagesStreamEvent::MessageDelta { delta, .. } => delta.stop_reason.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_params_from_conversation() {
+        let params = MessagesParamsBuilder::default()
+            .conversation(
+                &Conversation::new()
+                    .system("You are a pirate.")
+                    .user("Hello"),
+            )
+            .unwrap()
+            .max_tokens(100)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"anthropic_version":"bedrock-2023-05-31","max_tokens":100,"messages":[{"role":"user","content":[{"type":"text","text":"Hello"}]}],"system":"You are a pirate."}"#
+        );
+    }
+
+    #[test]
+    fn test_image_content_block() {
+        let block = ContentBlock::image(MediaType::Png, vec![0x89, 0x50, 0x4e, 0x47]);
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"type":"image","source":{"type":"base64","media_type":"image/png","data":"iVBORw=="}}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_response() {
+        let output = model_output(include_str!("../../tests/fixtures/anthropic/messages.json"));
+        let res = MessagesResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.role, Role::Assistant);
+        assert_eq!(res.text(), "Amazon Bedrock is a fully managed service.");
+        assert_eq!(res.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(
+            res.usage,
+            MessagesUsage {
+                input_tokens: 14,
+                output_tokens: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_stream_events() {
+        let events: Vec<MessagesStreamEvent> = [
+            r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-haiku-20240307","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":14,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":2}}"#,
+            r#"{"type":"message_stop","amazon-bedrock-invocationMetrics":{"inputTokenCount":14,"outputTokenCount":2,"invocationLatency":300,"firstByteLatency":200}}"#,
+        ]
+        .iter()
+        .map(|event| serde_json::from_str(event).unwrap())
+        .collect();
+
+        assert!(matches!(
+            events[0],
+            MessagesStreamEvent::MessageStart { .. }
+        ));
+        assert_eq!(events[2].text(), Some("Hello"));
+        assert_eq!(events[4].stop_reason(), Some("end_turn"));
+        assert!(matches!(events[5], MessagesStreamEvent::MessageStop));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::ai21::AI21LabsModel;
+use crate::amazon::{
+    AmazonModel, TitanEmbeddingModel, TitanEmbeddingV2Model, TitanImageModel,
+    TitanMultimodalEmbeddingModel, TitanTextModel,
+};
+use crate::anthropic::{AnthropicModel, ClaudeMessagesModel, ClaudeTextModel};
+use crate::cohere::{CohereChatModel, CohereCommandModel, CohereEmbedModel, CohereModel};
+use crate::meta::MetaModel;
+use crate::mistral::MistralModel;
+use crate::stability::StabilityAIModel;
+use crate::stream::StreamingModel;
+use crate::{BaseModel, BedrockModel};
+use std::any::TypeId;
+
+/// The kind of output a model produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modality {
+    Text,
+    Embedding,
+    Image,
+}
+
+/// What a model can do and the limits of its requests, so requests can be checked before they
+/// are built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    pub modality: Modality,
+
+    /// Whether the model can be invoked with [`crate::StoneMason::invoke_stream`].
+    pub streaming: bool,
+
+    /// Maximum number of input tokens, `None` for models which are not limited by tokens.
+    pub context_length: Option<u32>,
+
+    /// Maximum number of tokens the model can be asked to generate, `None` unless the model
+    /// generates text.
+    pub max_output_tokens: Option<u32>,
+
+    /// Length of the vectors returned, `None` unless the model generates embeddings.
+    pub embedding_dimension: Option<u32>,
+
+    /// The request params type, e.g. `TypeId::of::<AnthropicParams>()`.
+    pub params_type: TypeId,
+
+    /// The response type, e.g. `TypeId::of::<AnthropicResponse>()`.
+    pub response_type: TypeId,
+}
+
+/// Whether a model type implements [`StreamingModel`], which [`ModelCapabilities::streaming`]
+/// is taken from.
+trait Streaming: BedrockModel {
+    const STREAMING: bool;
+}
+
+impl<M: StreamingModel> Streaming for M {
+    const STREAMING: bool = true;
+}
+
+macro_rules! impl_not_streaming {
+    ($($model:ty),* $(,)?) => {
+        $(
+            impl Streaming for $model {
+                const STREAMING: bool = false;
+            }
+        )*
+    };
+}
+
+impl_not_streaming! {
+    AI21LabsModel,
+    TitanEmbeddingModel,
+    TitanEmbeddingV2Model,
+    TitanMultimodalEmbeddingModel,
+    TitanImageModel,
+    CohereEmbedModel,
+    StabilityAIModel,
+}
+
+impl ModelCapabilities {
+    fn text<M>(context_length: u32, max_output_tokens: u32) -> Self
+    where
+        M: Streaming,
+        M::Params: 'static,
+        M::Response: 'static,
+    {
+        ModelCapabilities {
+            modality: Modality::Text,
+            streaming: M::STREAMING,
+            context_length: Some(context_length),
+            max_output_tokens: Some(max_output_tokens),
+            embedding_dimension: None,
+            params_type: TypeId::of::<M::Params>(),
+            response_type: TypeId::of::<M::Response>(),
+        }
+    }
+
+    fn embedding<M>(context_length: u32, embedding_dimension: u32) -> Self
+    where
+        M: Streaming,
+        M::Params: 'static,
+        M::Response: 'static,
+    {
+        ModelCapabilities {
+            modality: Modality::Embedding,
+            streaming: M::STREAMING,
+            context_length: Some(context_length),
+            max_output_tokens: None,
+            embedding_dimension: Some(embedding_dimension),
+            params_type: TypeId::of::<M::Params>(),
+            response_type: TypeId::of::<M::Response>(),
+        }
+    }
+
+    fn image<M>() -> Self
+    where
+        M: Streaming,
+        M::Params: 'static,
+        M::Response: 'static,
+    {
+        ModelCapabilities {
+            modality: Modality::Image,
+            streaming: M::STREAMING,
+            context_length: None,
+            max_output_tokens: None,
+            embedding_dimension: None,
+            params_type: TypeId::of::<M::Params>(),
+            response_type: TypeId::of::<M::Response>(),
+        }
+    }
+}
+
+impl BaseModel {
+    /// What the model can do and the limits of its requests.
+    pub fn capabilities(&self) -> ModelCapabilities {
+        use ModelCapabilities as C;
+        match self {
+            BaseModel::AI21Labs(
+                AI21LabsModel::Jurassic2MidV1 | AI21LabsModel::Jurassic2UltraV1,
+            ) => C::text::<AI21LabsModel>(8192, 8191),
+            BaseModel::Amazon(model) => match model {
+                AmazonModel::TitanTextLiteV1 => C::text::<TitanTextModel>(4096, 4096),
+                AmazonModel::TitanTextExpressV1 | AmazonModel::TitanTextAgileV1 => {
+                    C::text::<TitanTextModel>(8192, 8192)
+                }
+                AmazonModel::TitanEmbeddingsTextV1 => {
+                    C::embedding::<TitanEmbeddingModel>(8192, 1536)
+                }
+                AmazonModel::TitanEmbeddingsTextV2 => {
+                    C::embedding::<TitanEmbeddingV2Model>(8192, 1024)
+                }
+                AmazonModel::TitanMultimodalEmbeddingsV1 => {
+                    C::embedding::<TitanMultimodalEmbeddingModel>(128, 1024)
+                }
+                AmazonModel::TitanImageGeneratorV1 => C::image::<TitanImageModel>(),
+            },
+            BaseModel::Anthropic(model) => match model {
+                AnthropicModel::ClaudeV1
+                | AnthropicModel::ClaudeV2
+                | AnthropicModel::ClaudeInstantV1 => C::text::<ClaudeTextModel>(100_000, 4096),
+                AnthropicModel::ClaudeV2_1 => C::text::<ClaudeTextModel>(200_000, 4096),
+                AnthropicModel::Claude3HaikuV1
+                | AnthropicModel::Claude3SonnetV1
+                | AnthropicModel::Claude3OpusV1 => C::text::<ClaudeMessagesModel>(200_000, 4096),
+            },
+            BaseModel::Cohere(model) => match model {
+                CohereModel::CommandV14 | CohereModel::CommandLightV14 => {
+                    C::text::<CohereCommandModel>(4096, 4096)
+                }
+                CohereModel::CommandRV1 | CohereModel::CommandRPlusV1 => {
+                    C::text::<CohereChatModel>(128_000, 4096)
+                }
+                CohereModel::EmbedEnglishV3 | CohereModel::EmbedMultilingualV3 => {
+                    C::embedding::<CohereEmbedModel>(512, 1024)
+                }
+            },
+            BaseModel::Meta(model) => match model {
+                MetaModel::Llama2Chat13BV1 | MetaModel::Llama2Chat70BV1 => {
+                    C::text::<MetaModel>(4096, 2048)
+                }
+                MetaModel::Llama3Instruct8BV1 | MetaModel::Llama3Instruct70BV1 => {
+                    C::text::<MetaModel>(8192, 2048)
+                }
+            },
+            BaseModel::Mistral(model) => match model {
+                MistralModel::Mistral7BInstructV0_2 | MistralModel::MistralLargeV1 => {
+                    C::text::<MistralModel>(32_000, 8192)
+                }
+                MistralModel::Mixtral8x7BInstructV0_1 => C::text::<MistralModel>(32_000, 4096),
+            },
+            BaseModel::StabilityAI(StabilityAIModel::StableDiffusionXLV0) => {
+                C::image::<StabilityAIModel>()
+            }
+        }
+    }
+}
+
+/// Every known model along with its capabilities.
+pub fn catalog() -> impl Iterator<Item = (BaseModel, ModelCapabilities)> {
+    BaseModel::all().map(|model| (model, model.capabilities()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::messages::MessagesParams;
+    use crate::anthropic::{AnthropicParams, AnthropicResponse};
+
+    #[test]
+    fn test_catalog_covers_every_model() {
+        assert_eq!(catalog().count(), BaseModel::all().count());
+
+        for (model, caps) in catalog() {
+            assert_eq!(
+                caps.max_output_tokens.is_some(),
+                caps.modality == Modality::Text,
+                "{model}"
+            );
+            assert_eq!(
+                caps.embedding_dimension.is_some(),
+                caps.modality == Modality::Embedding,
+                "{model}"
+            );
+            if let (Some(context), Some(output)) = (caps.context_length, caps.max_output_tokens) {
+                assert!(output <= context, "{model}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let caps = BaseModel::from(AnthropicModel::ClaudeV2).capabilities();
+        assert_eq!(caps.modality, Modality::Text);
+        assert!(caps.streaming);
+        assert_eq!(caps.context_length, Some(100_000));
+        assert_eq!(caps.params_type, TypeId::of::<AnthropicParams>());
+        assert_eq!(caps.response_type, TypeId::of::<AnthropicResponse>());
+
+        let caps = BaseModel::from(AnthropicModel::Claude3HaikuV1).capabilities();
+        assert_eq!(caps.params_type, TypeId::of::<MessagesParams>());
+
+        let caps = BaseModel::from(CohereModel::EmbedEnglishV3).capabilities();
+        assert_eq!(caps.modality, Modality::Embedding);
+        assert!(!caps.streaming);
+        assert_eq!(caps.embedding_dimension, Some(1024));
+
+        let caps = BaseModel::from(StabilityAIModel::StableDiffusionXLV0).capabilities();
+        assert_eq!(caps.modality, Modality::Image);
+        assert_eq!(caps.context_length, None);
+    }
+}
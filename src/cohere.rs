@@ -1,29 +1,104 @@
-use crate::ModelVersion;
-use crate::ModelVersion::{V14, V15, V3};
+use crate::stream::{StreamChunk, StreamingModel};
+use crate::{BedrockModel, FromModelOutput};
 use derive_builder::Builder;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fmt::{Display, Error, Formatter};
+use std::fmt::{Display, Formatter};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CohereModel {
-    Command(ModelVersion),
-    CommandLight(ModelVersion),
-    EmbedEnglish(ModelVersion),
-    EmbedMultilingual(ModelVersion),
+    CommandV14,
+    CommandLightV14,
+    CommandRV1,
+    CommandRPlusV1,
+    EmbedEnglishV3,
+    EmbedMultilingualV3,
+}
+
+impl CohereModel {
+    pub const ALL: [CohereModel; 6] = [
+        CohereModel::CommandV14,
+        CohereModel::CommandLightV14,
+        CohereModel::CommandRV1,
+        CohereModel::CommandRPlusV1,
+        CohereModel::EmbedEnglishV3,
+        CohereModel::EmbedMultilingualV3,
+    ];
+
+    pub const PROVIDER: &'static str = "cohere";
+
+    /// Full Bedrock model id, e.g. `cohere.command-text-v14`.
+    pub fn model_id(&self) -> String {
+        format!("{}.{self}", Self::PROVIDER)
+    }
 }
 
 impl Display for CohereModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            CohereModel::Command(v) if *v == V14 => write!(f, "command-text-v14"),
-            CohereModel::CommandLight(v) if *v == V15 => write!(f, "command-light-text-v14"),
-            CohereModel::EmbedEnglish(v) if *v == V3 => write!(f, "embed-english-v3"),
-            CohereModel::EmbedMultilingual(v) if *v == V3 => write!(f, "embed-multilingual-v3"),
-            _ => Err(Error),
-        }
+        let id = match self {
+            CohereModel::CommandV14 => "command-text-v14",
+            CohereModel::CommandLightV14 => "command-light-text-v14",
+            CohereModel::CommandRV1 => "command-r-v1:0",
+            CohereModel::CommandRPlusV1 => "command-r-plus-v1:0",
+            CohereModel::EmbedEnglishV3 => "embed-english-v3",
+            CohereModel::EmbedMultilingualV3 => "embed-multilingual-v3",
+        };
+        write!(f, "{id}")
     }
 }
 
+api_model! {
+    /// Command and Command Light, which take a prompt in [`CohereParams`].
+    pub enum CohereCommandModel: CohereModel, "CohereParams" {
+        CommandV14,
+        CommandLightV14,
+    }
+}
+
+impl BedrockModel for CohereCommandModel {
+    const PROVIDER: &'static str = CohereModel::PROVIDER;
+    type Params = CohereParams;
+    type Response = CohereResponse;
+}
+
+impl StreamingModel for CohereCommandModel {
+    type Chunk = CohereStreamChunk;
+    const STREAM_FIELD: bool = true;
+}
+
+api_model! {
+    /// Command R and R+, which take a chat in [`CohereChatParams`].
+    pub enum CohereChatModel: CohereModel, "CohereChatParams" {
+        CommandRV1,
+        CommandRPlusV1,
+    }
+}
+
+impl BedrockModel for CohereChatModel {
+    const PROVIDER: &'static str = CohereModel::PROVIDER;
+    type Params = CohereChatParams;
+    type Response = CohereChatResponse;
+}
+
+impl StreamingModel for CohereChatModel {
+    type Chunk = CohereChatStreamEvent;
+    const STREAM_FIELD: bool = true;
+}
+
+api_model! {
+    /// The Embed models, which take [`CohereEmbedParams`].
+    pub enum CohereEmbedModel: CohereModel, "CohereEmbedParams" {
+        EmbedEnglishV3,
+        EmbedMultilingualV3,
+    }
+}
+
+impl BedrockModel for CohereEmbedModel {
+    const PROVIDER: &'static str = CohereModel::PROVIDER;
+    type Params = CohereEmbedParams;
+    type Response = CohereEmbedResponse;
+}
+
 #[derive(Serialize, Builder)]
 #[builder(setter(strip_option))]
 pub struct CohereParams {
@@ -51,10 +126,6 @@ pub struct CohereParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     return_likelihoods: Option<ReturnLikelihoods>,
 
-    #[builder(default = "None")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    stream: Option<bool>,
-
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     num_generations: Option<i32>,
@@ -68,16 +139,423 @@ pub struct CohereParams {
     truncate: Option<Truncate>,
 }
 
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option))]
+pub struct CohereChatParams {
+    #[builder(setter(into))]
+    message: String,
+
+    /// Previous turns, oldest first, not including `message`.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chat_history: Option<Vec<CohereChatMessage>>,
+
+    /// Snippets to ground the reply in, cited in [`CohereChatResponse::citations`]. Each is a
+    /// map of string fields, e.g. `title` and `snippet`.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    documents: Option<Vec<HashMap<String, String>>>,
+
+    /// Replaces the default system preamble.
+    #[builder(default = "None", setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preamble: Option<String>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<f32>,
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    k: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+
+    /// Only generate search queries for `message`, instead of a reply.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_queries_only: Option<bool>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CohereChatMessage {
+    pub role: ChatRole,
+    pub message: String,
+}
+
+impl CohereChatMessage {
+    pub fn user(message: impl Into<String>) -> Self {
+        CohereChatMessage {
+            role: ChatRole::User,
+            message: message.into(),
+        }
+    }
+
+    pub fn chatbot(message: impl Into<String>) -> Self {
+        CohereChatMessage {
+            role: ChatRole::Chatbot,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChatRole {
+    User,
+    Chatbot,
+    System,
+}
+
+/// With `Generation`, only the generated tokens' likelihoods are returned; with `All`, the
+/// prompt's tokens are included too.
 #[derive(Serialize, Clone)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum ReturnLikelihoods {
     Generation,
     All,
     None,
 }
 
-#[derive(Serialize, Clone)]
+/// How inputs longer than the maximum token length are handled. `None` returns an error
+/// instead of truncating.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Truncate {
     None,
     Start,
     End,
 }
+
+#[derive(Serialize, Builder, Clone)]
+#[builder(setter(strip_option))]
+pub struct CohereEmbedParams {
+    texts: Vec<String>,
+    input_type: InputType,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    truncate: Option<Truncate>,
+}
+
+/// Distinguishes between the kinds of text being embedded, so the Embed v3 models can tailor
+/// the embeddings to how they will be used.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    SearchDocument,
+    SearchQuery,
+    Classification,
+    Clustering,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CohereEmbedResponse {
+    pub embeddings: Vec<Vec<f32>>,
+    pub id: String,
+    pub texts: Vec<String>,
+}
+
+impl<'de> FromModelOutput<'de, CohereEmbedResponse> for CohereEmbedResponse {}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CohereResponse {
+    pub id: String,
+    pub generations: Vec<Generation>,
+
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+impl<'de> FromModelOutput<'de, CohereResponse> for CohereResponse {}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CohereChatResponse {
+    pub response_id: String,
+    pub generation_id: String,
+    pub text: String,
+
+    /// One of `COMPLETE`, `MAX_TOKENS`, `ERROR` or `ERROR_TOXIC`.
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    /// The conversation so far, including `message` and this reply.
+    #[serde(default)]
+    pub chat_history: Option<Vec<CohereChatMessage>>,
+
+    /// Only populated when `documents` were sent.
+    #[serde(default)]
+    pub citations: Option<Vec<Citation>>,
+}
+
+impl<'de> FromModelOutput<'de, CohereChatResponse> for CohereChatResponse {}
+
+/// A span of the reply, by character offsets, supported by the documents with `document_ids`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Citation {
+    pub start: u32,
+    pub end: u32,
+    pub text: String,
+    pub document_ids: Vec<String>,
+}
+
+/// `finish_reason` is one of `COMPLETE`, `MAX_TOKENS`, `ERROR` or `ERROR_TOXIC`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Generation {
+    pub id: String,
+    pub text: String,
+
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    /// Only populated when `return_likelihoods` is `Generation` or `All`.
+    #[serde(default)]
+    pub likelihood: Option<f32>,
+
+    /// Only populated when `return_likelihoods` is `Generation` or `All`.
+    #[serde(default)]
+    pub token_likelihoods: Option<Vec<TokenLikelihood>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenLikelihood {
+    pub token: String,
+    pub likelihood: f32,
+}
+
+/// Every event carries the next piece of `text`, until the last one which has `is_finished`
+/// set along with the `finish_reason` and full `response`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CohereStreamChunk {
+    #[serde(default)]
+    pub text: Option<String>,
+
+    pub is_finished: bool,
+
+    /// Which generation the text belongs to, when `num_generations` is greater than one.
+    #[serde(default)]
+    pub index: Option<u32>,
+
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    #[serde(default)]
+    pub response: Option<CohereResponse>,
+}
+
+impl StreamChunk for CohereStreamChunk {
+    fn stop_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+}
+
+/// An event of a streamed Command R response, ending with `StreamEnd`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "event_type", rename_all = "kebab-case")]
+pub enum CohereChatStreamEvent {
+    StreamStart {
+        generation_id: String,
+    },
+
+    TextGeneration {
+        text: String,
+    },
+
+    CitationGeneration {
+        citations: Vec<Citation>,
+    },
+
+    StreamEnd {
+        finish_reason: String,
+        response: CohereChatResponse,
+    },
+
+    /// Events which only occur with connectors or tools, such as search results.
+    #[serde(other)]
+    Other,
+}
+
+impl CohereChatStreamEvent {
+    /// The text added by this event, if any.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            CohereChatStreamEvent::TextGeneration { text } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl StreamChunk for CohereChatStreamEvent {
+    fn stop_reason(&self) -> Option<&str> {
+        match self {
+            CohereChatStreamEvent::StreamEnd { finish_reason, .. } => Some(finish_reason),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_serialize_return_likelihoods() {
+        let params = CohereParamsBuilder::default()
+            .prompt("Hello".to_string())
+            .return_likelihoods(ReturnLikelihoods::All)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"prompt":"Hello","return_likelihoods":"ALL"}"#
+        );
+    }
+
+    #[test]
+    fn test_serialize_embed_params() {
+        let params = CohereEmbedParamsBuilder::default()
+            .texts(vec!["first".to_string(), "second".to_string()])
+            .input_type(InputType::SearchDocument)
+            .truncate(Truncate::End)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"texts":["first","second"],"input_type":"search_document","truncate":"END"}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_embed_response() {
+        let output = model_output(include_str!("../tests/fixtures/cohere/embed.json"));
+        let res = CohereEmbedResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.texts, vec!["first", "second"]);
+        assert_eq!(res.embeddings.len(), 2);
+        assert_eq!(res.embeddings[1], vec![0.0234375, -0.0340271, 0.0152435]);
+    }
+
+    #[test]
+    fn test_deserialize_generation() {
+        let output = model_output(include_str!("../tests/fixtures/cohere/generation.json"));
+        let res = CohereResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.id, "3a5b8c9d-1e2f-4a6b-8c7d-9e0f1a2b3c4d");
+        assert_eq!(res.prompt.as_deref(), Some("Write a haiku about rust"));
+        assert_eq!(res.generations.len(), 1);
+
+        let generation = &res.generations[0];
+        assert_eq!(generation.finish_reason.as_deref(), Some("COMPLETE"));
+        assert!(generation.likelihood.is_none());
+        assert!(generation.token_likelihoods.is_none());
+    }
+
+    #[test]
+    fn test_serialize_chat_params() {
+        let params = CohereChatParamsBuilder::default()
+            .message("Which is the tallest?")
+            .chat_history(vec![
+                CohereChatMessage::user("Name two mountains"),
+                CohereChatMessage::chatbot("Everest and K2."),
+            ])
+            .documents(vec![HashMap::from([(
+                "snippet".to_string(),
+                "Everest is 8849m tall.".to_string(),
+            )])])
+            .preamble("Be brief.")
+            .max_tokens(100)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"message":"Which is the tallest?","chat_history":[{"role":"USER","message":"Name two mountains"},{"role":"CHATBOT","message":"Everest and K2."}],"documents":[{"snippet":"Everest is 8849m tall."}],"preamble":"Be brief.","max_tokens":100}"#
+        );
+    }
+
+    #[test]
+    fn test_api_models() {
+        assert_eq!(
+            CohereModel::from(CohereChatModel::CommandRPlusV1),
+            CohereModel::CommandRPlusV1
+        );
+        assert_eq!(
+            CohereEmbedModel::try_from(CohereModel::EmbedEnglishV3).unwrap(),
+            CohereEmbedModel::EmbedEnglishV3
+        );
+        assert_eq!(
+            CohereChatModel::try_from(CohereModel::CommandV14)
+                .unwrap_err()
+                .to_string(),
+            "cohere.command-text-v14 does not support CohereChatParams"
+        );
+        assert!("cohere.command-r-v1:0"
+            .parse::<CohereCommandModel>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_chat_response() {
+        let output = model_output(include_str!("../tests/fixtures/cohere/chat.json"));
+        let res = CohereChatResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.text, "Everest, at 8849m.");
+        assert_eq!(res.finish_reason.as_deref(), Some("COMPLETE"));
+        assert_eq!(res.chat_history.unwrap().len(), 4);
+        assert_eq!(res.citations.unwrap()[0].document_ids, vec!["doc_0"]);
+    }
+
+    #[test]
+    fn test_deserialize_stream_events() {
+        let events: Vec<CohereChatStreamEvent> = [
+            r#"{"is_finished":false,"event_type":"stream-start","generation_id":"a1b2"}"#,
+            r#"{"is_finished":false,"event_type":"text-generation","text":"Everest"}"#,
+            r#"{"is_finished":false,"event_type":"search-queries-generation","search_queries":[]}"#,
+            r#"{"is_finished":true,"event_type":"stream-end","finish_reason":"COMPLETE","response":{"response_id":"c3d4","generation_id":"a1b2","text":"Everest"}}"#,
+        ]
+        .iter()
+        .map(|event| serde_json::from_str(event).unwrap())
+        .collect();
+
+        assert_eq!(events[1].text(), Some("Everest"));
+        assert!(matches!(events[2], CohereChatStreamEvent::Other));
+        assert_eq!(events[3].stop_reason(), Some("COMPLETE"));
+
+        let chunk: CohereStreamChunk =
+            serde_json::from_str(r#"{"is_finished":false,"text":" Hello"}"#).unwrap();
+        assert_eq!(chunk.text.as_deref(), Some(" Hello"));
+        assert_eq!(chunk.stop_reason(), None);
+    }
+
+    #[test]
+    fn test_deserialize_likelihoods() {
+        let output = model_output(include_str!("../tests/fixtures/cohere/likelihoods.json"));
+        let res = CohereResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.generations.len(), 2);
+
+        let generation = &res.generations[1];
+        assert_eq!(generation.finish_reason.as_deref(), Some("MAX_TOKENS"));
+        assert_eq!(generation.likelihood, Some(-1.4072266));
+
+        let token_likelihoods = generation.token_likelihoods.as_ref().unwrap();
+        assert_eq!(token_likelihoods.len(), 4);
+        assert_eq!(token_likelihoods[0].token, "The");
+        assert_eq!(token_likelihoods[0].likelihood, -6.59375);
+    }
+}
@@ -0,0 +1,818 @@
+use crate::ai21::{
+    AI21InferenceParameters, AI21InferenceParametersBuilder, AI21Response, AI21Token,
+};
+use crate::amazon::{
+    AmazonParams, AmazonParamsBuilder, TextGenerationConfigBuilder, TitanTextModel,
+    TitanTextResponse,
+};
+use crate::anthropic::messages::{MessagesParams, MessagesParamsBuilder, MessagesResponse};
+use crate::anthropic::{
+    AnthropicParams, AnthropicParamsBuilder, AnthropicResponse, ClaudeMessagesModel,
+    ClaudeTextModel,
+};
+use crate::cohere::{
+    CohereChatMessage, CohereChatModel, CohereChatParams, CohereChatParamsBuilder,
+    CohereChatResponse, CohereCommandModel, CohereParams, CohereParamsBuilder, CohereResponse,
+};
+use crate::identifier::ModelIdentifier;
+use crate::meta::{MetaModel, MetaParams, MetaParamsBuilder, MetaResponse};
+use crate::mistral::{MistralParams, MistralParamsBuilder, MistralResponse};
+use crate::{BaseModel, Result, StoneMason, StoneMasonError};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+/// A provider-agnostic text generation request, which can be translated into the params of
+/// any of the text generation models.
+///
+/// ```
+/// use stone_mason::completion::CompletionRequestBuilder;
+///
+/// let request = CompletionRequestBuilder::default()
+///     .system("You are a terse assistant.")
+///     .prompt("What is Amazon Bedrock?")
+///     .max_tokens(200)
+///     .temperature(0.5)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Clone, Debug)]
+#[builder(setter(strip_option))]
+pub struct CompletionRequest {
+    #[builder(default = "None", setter(into))]
+    pub system: Option<String>,
+
+    /// The conversation so far, which should end with a [`Role::User`] message.
+    pub messages: Vec<Message>,
+
+    pub max_tokens: u32,
+
+    #[builder(default = "None")]
+    pub temperature: Option<f32>,
+
+    #[builder(default = "None")]
+    pub top_p: Option<f32>,
+
+    /// Not supported by the Meta models, which ignore it.
+    #[builder(default = "Vec::new()", setter(custom))]
+    pub stop_sequences: Vec<String>,
+}
+
+impl CompletionRequestBuilder {
+    /// Sets the conversation to a single user message.
+    pub fn prompt(&mut self, prompt: impl Into<String>) -> &mut Self {
+        self.messages = Some(vec![Message::user(prompt)]);
+        self
+    }
+
+    pub fn stop_sequences<S: Into<String>>(
+        &mut self,
+        stop_sequences: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        self.stop_sequences = Some(stop_sequences.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+/// Checks that `messages` starts with a user turn and alternates between user and assistant
+/// turns, as the chat prompt formats of every provider require.
+pub(crate) fn validate_turns(messages: &[Message]) -> Result<()> {
+    let Some(first) = messages.first() else {
+        return Err(StoneMasonError::InvalidConversation(
+            "conversation must contain at least one user turn".to_string(),
+        ));
+    };
+    if first.role != Role::User {
+        return Err(StoneMasonError::InvalidConversation(
+            "conversation must start with a user turn".to_string(),
+        ));
+    }
+    if let Some(pair) = messages.windows(2).find(|w| w[0].role == w[1].role) {
+        return Err(StoneMasonError::InvalidConversation(format!(
+            "conversation turns must alternate, found two {:?} turns in a row",
+            pair[0].role
+        )));
+    }
+    Ok(())
+}
+
+impl Message {
+    pub fn user(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// A system prompt and the turns of a chat, which each provider renders into its own prompt
+/// format, e.g. [`crate::anthropic::render_prompt`] or [`crate::amazon::render_prompt`].
+///
+/// ```
+/// use stone_mason::completion::Conversation;
+///
+/// let conversation = Conversation::new()
+///     .system("You are a pirate.")
+///     .user("What is Amazon Bedrock?")
+///     .assistant("Arr,");
+///
+/// assert_eq!(conversation.system_prompt(), Some("You are a pirate."));
+/// assert_eq!(conversation.messages().len(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conversation {
+    system: Option<String>,
+    messages: Vec<Message>,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_messages(system: Option<String>, messages: Vec<Message>) -> Self {
+        Conversation { system, messages }
+    }
+
+    pub fn system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    pub fn user(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(Message::user(content));
+        self
+    }
+
+    /// Adds an assistant turn. When it is the last turn, it is left open as a prefill which
+    /// the model continues from.
+    pub fn assistant(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(Message::assistant(content));
+        self
+    }
+
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.system.as_deref()
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+}
+
+/// A provider-agnostic text generation response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionResponse {
+    pub text: String,
+    pub stop_reason: StopReason,
+
+    /// Not every provider reports token counts in the response body.
+    pub usage: Option<Usage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The model finished its response.
+    EndTurn,
+    /// The response reached `max_tokens`.
+    MaxTokens,
+    /// The response ended on one of the `stop_sequences`.
+    StopSequence,
+    /// The response was blocked by the provider's content filter.
+    ContentFiltered,
+    /// A stop reason not covered above, as reported by the provider.
+    Other(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl CompletionRequest {
+    fn conversation(&self) -> Conversation {
+        Conversation::from_messages(self.system.clone(), self.messages.clone())
+    }
+
+    fn max_tokens_i32(&self) -> Result<i32> {
+        i32::try_from(self.max_tokens).map_err(|_| {
+            StoneMasonError::invalid_params(format!("max_tokens {} is too large", self.max_tokens))
+        })
+    }
+
+    pub fn to_anthropic(&self) -> Result<AnthropicParams> {
+        let mut builder = AnthropicParamsBuilder::default();
+        builder
+            .conversation(&self.conversation())?
+            .max_tokens_to_sample(self.max_tokens);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.top_p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop_sequences(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn to_anthropic_messages(&self) -> Result<MessagesParams> {
+        let mut builder = MessagesParamsBuilder::default();
+        builder
+            .conversation(&self.conversation())?
+            .max_tokens(self.max_tokens);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.top_p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop_sequences(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// The prompt is rendered with the chat template of `model`.
+    pub fn to_meta(&self, model: &MetaModel) -> Result<MetaParams> {
+        let mut builder = MetaParamsBuilder::default();
+        builder
+            .conversation_for(model, &self.conversation())?
+            .max_gen_len(self.max_tokens_i32()?);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.top_p(top_p);
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn to_mistral(&self) -> Result<MistralParams> {
+        let mut builder = MistralParamsBuilder::default();
+        builder
+            .conversation(&self.conversation())?
+            .max_tokens(self.max_tokens);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.top_p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn to_titan(&self) -> Result<AmazonParams> {
+        let mut config = TextGenerationConfigBuilder::default();
+        config
+            .max_token_count(self.max_tokens)
+            .stop_sequences(self.stop_sequences.clone());
+        if let Some(temperature) = self.temperature {
+            config.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p(top_p);
+        }
+        Ok(AmazonParamsBuilder::default()
+            .conversation(&self.conversation())?
+            .text_generation_config(config.build()?)
+            .build()?)
+    }
+
+    pub fn to_cohere(&self) -> Result<CohereParams> {
+        let mut builder = CohereParamsBuilder::default();
+        builder
+            .prompt(render_transcript(self, "User", "Chatbot"))
+            .max_tokens(self.max_tokens_i32()?);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop_sequences(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// The last turn becomes the `message`, earlier turns the `chat_history` and the system
+    /// prompt the `preamble`. The last turn must be from the user, since Command R has no way
+    /// to continue an assistant turn.
+    pub fn to_cohere_chat(&self) -> Result<CohereChatParams> {
+        validate_turns(&self.messages)?;
+        let Some((last, history)) = self.messages.split_last() else {
+            unreachable!("validate_turns rejects empty conversations")
+        };
+        if last.role != Role::User {
+            return Err(StoneMasonError::InvalidConversation(
+                "conversation must end with a user turn".to_string(),
+            ));
+        }
+
+        let mut builder = CohereChatParamsBuilder::default();
+        builder
+            .message(last.content.clone())
+            .max_tokens(self.max_tokens_i32()?);
+        if !history.is_empty() {
+            builder.chat_history(
+                history
+                    .iter()
+                    .map(|message| match message.role {
+                        Role::User => CohereChatMessage::user(&message.content),
+                        Role::Assistant => CohereChatMessage::chatbot(&message.content),
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(system) = &self.system {
+            builder.preamble(system.clone());
+        }
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop_sequences(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn to_ai21(&self) -> Result<AI21InferenceParameters> {
+        let mut builder = AI21InferenceParametersBuilder::default();
+        builder
+            .prompt(render_transcript(self, "User", "Assistant"))
+            .max_tokens(self.max_tokens);
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder.top_p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder.stop_sequences(self.stop_sequences.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// Renders a plain `{user}: ...\n{assistant}: ...` transcript, for models without a dedicated
+/// chat format. A lone user message without a system prompt is sent as-is.
+fn render_transcript(request: &CompletionRequest, user: &str, assistant: &str) -> String {
+    if let (None, [message]) = (&request.system, request.messages.as_slice()) {
+        if message.role == Role::User {
+            return message.content.clone();
+        }
+    }
+
+    let mut lines = Vec::new();
+    if let Some(system) = &request.system {
+        lines.push(format!("{system}\n"));
+    }
+    for message in &request.messages {
+        let role = match message.role {
+            Role::User => user,
+            Role::Assistant => assistant,
+        };
+        lines.push(format!("{role}: {}", message.content));
+    }
+    lines.push(format!("{assistant}:"));
+    lines.join("\n")
+}
+
+impl From<AnthropicResponse> for CompletionResponse {
+    fn from(res: AnthropicResponse) -> Self {
+        let stop_reason = match res.stop_reason.as_str() {
+            "stop_sequence" => StopReason::StopSequence,
+            "max_tokens" => StopReason::MaxTokens,
+            other => StopReason::Other(other.to_string()),
+        };
+        CompletionResponse {
+            text: res.completion,
+            stop_reason,
+            usage: None,
+        }
+    }
+}
+
+impl From<MessagesResponse> for CompletionResponse {
+    fn from(res: MessagesResponse) -> Self {
+        let stop_reason = match res.stop_reason.as_deref() {
+            Some("end_turn") | None => StopReason::EndTurn,
+            Some("max_tokens") => StopReason::MaxTokens,
+            Some("stop_sequence") => StopReason::StopSequence,
+            Some(other) => StopReason::Other(other.to_string()),
+        };
+        CompletionResponse {
+            text: res.text(),
+            stop_reason,
+            usage: Some(Usage {
+                input_tokens: res.usage.input_tokens,
+                output_tokens: res.usage.output_tokens,
+            }),
+        }
+    }
+}
+
+impl From<MetaResponse> for CompletionResponse {
+    fn from(res: MetaResponse) -> Self {
+        let stop_reason = match res.stop_reason.as_str() {
+            "stop" => StopReason::EndTurn,
+            "length" => StopReason::MaxTokens,
+            other => StopReason::Other(other.to_string()),
+        };
+        CompletionResponse {
+            text: res.generation,
+            stop_reason,
+            usage: Some(Usage {
+                input_tokens: res.prompt_token_count,
+                output_tokens: res.generation_token_count,
+            }),
+        }
+    }
+}
+
+impl TryFrom<TitanTextResponse> for CompletionResponse {
+    type Error = StoneMasonError;
+
+    fn try_from(res: TitanTextResponse) -> Result<Self> {
+        let result = res.results.into_iter().next().ok_or_else(|| {
+            StoneMasonError::UnexpectedResponse("Titan response contained no results".to_string())
+        })?;
+        let stop_reason = match result.completion_reason.as_str() {
+            "FINISH" => StopReason::EndTurn,
+            "LENGTH" => StopReason::MaxTokens,
+            "STOP_CRITERIA_MET" => StopReason::StopSequence,
+            "CONTENT_FILTERED" => StopReason::ContentFiltered,
+            other => StopReason::Other(other.to_string()),
+        };
+        Ok(CompletionResponse {
+            text: result.output_text,
+            stop_reason,
+            usage: Some(Usage {
+                input_tokens: res.input_text_token_count,
+                output_tokens: result.token_count,
+            }),
+        })
+    }
+}
+
+impl TryFrom<CohereResponse> for CompletionResponse {
+    type Error = StoneMasonError;
+
+    fn try_from(res: CohereResponse) -> Result<Self> {
+        let generation = res.generations.into_iter().next().ok_or_else(|| {
+            StoneMasonError::UnexpectedResponse(
+                "Cohere response contained no generations".to_string(),
+            )
+        })?;
+        Ok(CompletionResponse {
+            text: generation.text,
+            stop_reason: cohere_stop_reason(generation.finish_reason.as_deref()),
+            usage: None,
+        })
+    }
+}
+
+fn cohere_stop_reason(finish_reason: Option<&str>) -> StopReason {
+    match finish_reason {
+        Some("COMPLETE") | None => StopReason::EndTurn,
+        Some("MAX_TOKENS") => StopReason::MaxTokens,
+        Some("ERROR_TOXIC") => StopReason::ContentFiltered,
+        Some(other) => StopReason::Other(other.to_string()),
+    }
+}
+
+impl From<CohereChatResponse> for CompletionResponse {
+    fn from(res: CohereChatResponse) -> Self {
+        CompletionResponse {
+            text: res.text,
+            stop_reason: cohere_stop_reason(res.finish_reason.as_deref()),
+            usage: None,
+        }
+    }
+}
+
+impl TryFrom<MistralResponse> for CompletionResponse {
+    type Error = StoneMasonError;
+
+    fn try_from(res: MistralResponse) -> Result<Self> {
+        let output = res.outputs.into_iter().next().ok_or_else(|| {
+            StoneMasonError::UnexpectedResponse("Mistral response contained no outputs".to_string())
+        })?;
+        let stop_reason = match output.stop_reason.as_deref() {
+            Some("stop") | None => StopReason::EndTurn,
+            Some("length") => StopReason::MaxTokens,
+            Some(other) => StopReason::Other(other.to_string()),
+        };
+        Ok(CompletionResponse {
+            text: output.text,
+            stop_reason,
+            usage: None,
+        })
+    }
+}
+
+impl TryFrom<AI21Response> for CompletionResponse {
+    type Error = StoneMasonError;
+
+    fn try_from(res: AI21Response) -> Result<Self> {
+        let token_count = |tokens: &[AI21Token]| {
+            u32::try_from(tokens.len()).map_err(|_| {
+                StoneMasonError::UnexpectedResponse("AI21 token count overflows u32".to_string())
+            })
+        };
+        let input_tokens = token_count(&res.prompt.tokens)?;
+        let completion = res.completions.into_iter().next().ok_or_else(|| {
+            StoneMasonError::UnexpectedResponse(
+                "AI21 response contained no completions".to_string(),
+            )
+        })?;
+        let stop_reason = match completion.finish_reason.reason.as_str() {
+            "endoftext" => StopReason::EndTurn,
+            "length" => StopReason::MaxTokens,
+            "stop" => StopReason::StopSequence,
+            other => StopReason::Other(other.to_string()),
+        };
+        Ok(CompletionResponse {
+            text: completion.data.text,
+            stop_reason,
+            usage: Some(Usage {
+                input_tokens,
+                output_tokens: token_count(&completion.data.tokens)?,
+            }),
+        })
+    }
+}
+
+impl StoneMason {
+    /// Invokes any of the text generation models with a provider-agnostic request, so the model
+    /// can be switched without changing any other code. `model` may also be a provisioned,
+    /// custom or inference profile deployment of one of them.
+    pub async fn complete(
+        &self,
+        model: &ModelIdentifier,
+        request: &CompletionRequest,
+    ) -> Result<CompletionResponse> {
+        let unsupported = || StoneMasonError::UnsupportedOperation {
+            model: model.model_id(),
+            operation: "text generation",
+        };
+        match *model.base() {
+            BaseModel::Anthropic(base) => {
+                if let Ok(base) = ClaudeTextModel::try_from(base) {
+                    let res = self
+                        .invoke(&model.with_base(base), &request.to_anthropic()?)
+                        .await?;
+                    return Ok(res.into());
+                }
+                let base = ClaudeMessagesModel::try_from(base).map_err(|_| unsupported())?;
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_anthropic_messages()?)
+                    .await?;
+                Ok(res.into())
+            }
+            BaseModel::Meta(base) => {
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_meta(&base)?)
+                    .await?;
+                Ok(res.into())
+            }
+            BaseModel::Mistral(base) => {
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_mistral()?)
+                    .await?;
+                res.try_into()
+            }
+            BaseModel::AI21Labs(base) => {
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_ai21()?)
+                    .await?;
+                res.try_into()
+            }
+            BaseModel::StabilityAI(_) => Err(unsupported()),
+            BaseModel::Amazon(base) => {
+                let base = TitanTextModel::try_from(base).map_err(|_| unsupported())?;
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_titan()?)
+                    .await?;
+                res.try_into()
+            }
+            BaseModel::Cohere(base) => {
+                if let Ok(base) = CohereChatModel::try_from(base) {
+                    let res = self
+                        .invoke(&model.with_base(base), &request.to_cohere_chat()?)
+                        .await?;
+                    return Ok(res.into());
+                }
+                let base = CohereCommandModel::try_from(base).map_err(|_| unsupported())?;
+                let res = self
+                    .invoke(&model.with_base(base), &request.to_cohere()?)
+                    .await?;
+                res.try_into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+    use crate::FromModelOutput;
+
+    fn conversation() -> CompletionRequest {
+        CompletionRequestBuilder::default()
+            .system("You are a helpful assistant.")
+            .messages(vec![
+                Message::user("Hi"),
+                Message::assistant("Hello! How can I help?"),
+                Message::user("Tell me a joke"),
+            ])
+            .max_tokens(100)
+            .temperature(0.5)
+            .stop_sequences(["###"])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_to_anthropic() {
+        let params = serde_json::to_value(conversation().to_anthropic().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "prompt": "You are a helpful assistant.\n\nHuman: Hi\n\nAssistant: Hello! How can I help?\n\nHuman: Tell me a joke\n\nAssistant:",
+                "max_tokens_to_sample": 100,
+                "temperature": 0.5,
+                "stop_sequences": ["###"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_meta() {
+        let params =
+            serde_json::to_value(conversation().to_meta(&MetaModel::Llama2Chat13BV1).unwrap())
+                .unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "prompt": "<s>[INST] <<SYS>>\nYou are a helpful assistant.\n<</SYS>>\n\nHi [/INST] Hello! How can I help? </s><s>[INST] Tell me a joke [/INST]",
+                "temperature": 0.5,
+                "max_gen_len": 100,
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_titan() {
+        let params = serde_json::to_value(conversation().to_titan().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "inputText": "You are a helpful assistant.\n\nUser: Hi\nBot: Hello! How can I help?\nUser: Tell me a joke\nBot:",
+                "textGenerationConfig": {
+                    "temperature": 0.5,
+                    "maxTokenCount": 100,
+                    "stopSequences": ["###"],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_mistral() {
+        let params = serde_json::to_value(conversation().to_mistral().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "prompt": "<s>[INST] You are a helpful assistant.\n\nHi [/INST] Hello! How can I help?</s>[INST] Tell me a joke [/INST]",
+                "max_tokens": 100,
+                "stop": ["###"],
+                "temperature": 0.5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_cohere_chat() {
+        let params = serde_json::to_value(conversation().to_cohere_chat().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "message": "Tell me a joke",
+                "chat_history": [
+                    {"role": "USER", "message": "Hi"},
+                    {"role": "CHATBOT", "message": "Hello! How can I help?"},
+                ],
+                "preamble": "You are a helpful assistant.",
+                "max_tokens": 100,
+                "temperature": 0.5,
+                "stop_sequences": ["###"],
+            })
+        );
+
+        let request = CompletionRequestBuilder::default()
+            .messages(vec![Message::user("Hi"), Message::assistant("Hello")])
+            .max_tokens(100)
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.to_cohere_chat().unwrap_err().to_string(),
+            "conversation must end with a user turn"
+        );
+    }
+
+    #[test]
+    fn test_single_prompt_is_sent_as_is() {
+        let request = CompletionRequestBuilder::default()
+            .prompt("Tell me a joke")
+            .max_tokens(100)
+            .build()
+            .unwrap();
+
+        let params = serde_json::to_value(request.to_cohere().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({"prompt": "Tell me a joke", "max_tokens": 100})
+        );
+
+        let params = serde_json::to_value(request.to_ai21().unwrap()).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({"prompt": "Tell me a joke", "maxTokens": 100})
+        );
+    }
+
+    #[test]
+    fn test_from_responses() {
+        let output = model_output(include_str!("../tests/fixtures/meta/llama2_chat.json"));
+        let res: CompletionResponse = MetaResponse::from_model_output(&output).unwrap().into();
+        assert_eq!(res.stop_reason, StopReason::EndTurn);
+        assert_eq!(
+            res.usage,
+            Some(Usage {
+                input_tokens: 24,
+                output_tokens: 61
+            })
+        );
+
+        let output = model_output(include_str!("../tests/fixtures/amazon/titan_text.json"));
+        let res: CompletionResponse = TitanTextResponse::from_model_output(&output)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert!(res.text.starts_with("\nAmazon Bedrock"));
+        assert_eq!(res.stop_reason, StopReason::EndTurn);
+
+        let output = model_output(include_str!("../tests/fixtures/cohere/likelihoods.json"));
+        let res: CompletionResponse = CohereResponse::from_model_output(&output)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(res.text, " Rust is a");
+        assert_eq!(res.stop_reason, StopReason::MaxTokens);
+        assert_eq!(res.usage, None);
+
+        let output = model_output(include_str!("../tests/fixtures/ai21/completion.json"));
+        let res: CompletionResponse = AI21Response::from_model_output(&output)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(res.stop_reason, StopReason::EndTurn);
+        assert_eq!(
+            res.usage,
+            Some(Usage {
+                input_tokens: 3,
+                output_tokens: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,540 @@
+//! Embeds any number of texts with any of the text embeddings models, batching and running
+//! requests concurrently as each model allows.
+
+use crate::amazon::{
+    EmbeddingConfig, TitanEmbeddingModel, TitanEmbeddingParams, TitanEmbeddingParamsBuilder,
+    TitanEmbeddingResponse, TitanEmbeddingV2Model, TitanEmbeddingV2Params,
+    TitanEmbeddingV2ParamsBuilder, TitanMultimodalEmbeddingModel, TitanMultimodalEmbeddingParams,
+    TitanMultimodalEmbeddingParamsBuilder,
+};
+use crate::cohere::{
+    CohereEmbedModel, CohereEmbedParams, CohereEmbedParamsBuilder, CohereEmbedResponse,
+    CohereModel, InputType, Truncate,
+};
+use crate::{BaseModel, BedrockModel, Result, StoneMason, StoneMasonError};
+use futures::{StreamExt, TryStreamExt};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+
+/// An embeddings model along with the settings of its requests, which can embed a batch of
+/// texts per request.
+pub trait Embedder: BedrockModel {
+    /// The most texts a single request can embed.
+    fn max_batch_size(&self) -> usize;
+
+    /// Length of the embeddings returned.
+    fn dimension(&self) -> usize;
+
+    /// The request body embedding `texts`, of which there are at most `max_batch_size`.
+    fn batch_params(&self, texts: &[&str]) -> Result<Self::Params>;
+
+    /// The embeddings in `response`, in the order of the texts sent.
+    fn batch_embeddings(&self, response: Self::Response) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Embeddings of a list of texts, in the order of the texts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embeddings {
+    /// Id of the model which generated the embeddings, e.g. `cohere.embed-english-v3`.
+    pub model_id: String,
+
+    /// Length of every embedding.
+    pub dimension: usize,
+
+    pub vectors: Vec<Vec<f32>>,
+}
+
+impl StoneMason {
+    /// Embeds `texts` with `embedder`, splitting them into batches of the model's batch size
+    /// and running at most `concurrency` requests at once. Every embedding is checked to be of
+    /// the model's dimension.
+    ///
+    /// ```no_run
+    /// # async fn example(client: stone_mason::StoneMason) -> stone_mason::Result<()> {
+    /// use stone_mason::cohere::{CohereEmbedModel, InputType};
+    /// use stone_mason::embedding::CohereEmbedder;
+    ///
+    /// let embedder = CohereEmbedder::new(CohereEmbedModel::EmbedEnglishV3, InputType::SearchDocument);
+    /// let embeddings = client.embed(&embedder, &["first", "second"], 4).await?;
+    /// assert_eq!(embeddings.vectors.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn embed<E: Embedder>(
+        &self,
+        embedder: &E,
+        texts: &[impl AsRef<str>],
+        concurrency: usize,
+    ) -> Result<Embeddings> {
+        let texts: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+        let vectors = embed_batches(
+            &texts,
+            embedder.max_batch_size(),
+            concurrency,
+            embedder.dimension(),
+            |batch| async move {
+                let params = embedder.batch_params(batch)?;
+                embedder.batch_embeddings(self.invoke(embedder, &params).await?)
+            },
+        )
+        .await?;
+        Ok(Embeddings {
+            model_id: embedder.model_id(),
+            dimension: embedder.dimension(),
+            vectors,
+        })
+    }
+}
+
+/// Runs `embed_batch` over batches of `texts`, at most `concurrency` at a time, checking the
+/// number and length of the embeddings returned for each.
+async fn embed_batches<'a, F, Fut>(
+    texts: &'a [&'a str],
+    batch_size: usize,
+    concurrency: usize,
+    dimension: usize,
+    embed_batch: F,
+) -> Result<Vec<Vec<f32>>>
+where
+    F: Fn(&'a [&'a str]) -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<f32>>>>,
+{
+    let batches: Vec<Vec<Vec<f32>>> = futures::stream::iter(texts.chunks(batch_size.max(1)))
+        .map(|batch| {
+            let embeddings = embed_batch(batch);
+            async move {
+                let embeddings = embeddings.await?;
+                if embeddings.len() != batch.len() {
+                    return Err(StoneMasonError::UnexpectedResponse(format!(
+                        "expected {} embeddings, got {}",
+                        batch.len(),
+                        embeddings.len()
+                    )));
+                }
+                if let Some(embedding) = embeddings.iter().find(|e| e.len() != dimension) {
+                    return Err(StoneMasonError::UnexpectedResponse(format!(
+                        "expected an embedding of length {dimension}, got {}",
+                        embedding.len()
+                    )));
+                }
+                Ok(embeddings)
+            }
+        })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await?;
+    Ok(batches.into_iter().flatten().collect())
+}
+
+fn catalog_dimension(model: impl Into<BaseModel>) -> usize {
+    let dimension = model.into().capabilities().embedding_dimension;
+    dimension.expect("embedders are only constructed for embeddings models") as usize
+}
+
+/// Embeds one text per request with any of the Titan embeddings models.
+///
+/// ```
+/// use stone_mason::amazon::TitanEmbeddingV2Model;
+/// use stone_mason::embedding::{Embedder, TitanEmbedder};
+///
+/// let embedder = TitanEmbedder::new(TitanEmbeddingV2Model::TitanEmbeddingsTextV2)
+///     .with_dimension(256)
+///     .normalize(true);
+/// assert_eq!(embedder.dimension(), 256);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TitanEmbedder<M> {
+    model: M,
+    dimension: Option<u32>,
+    normalize: Option<bool>,
+}
+
+impl<M: Copy> TitanEmbedder<M> {
+    pub fn new(model: M) -> Self {
+        TitanEmbedder {
+            model,
+            dimension: None,
+            normalize: None,
+        }
+    }
+
+    /// Requests embeddings of length `dimension` instead of the model's default. Titan Text
+    /// Embeddings V2 supports 256, 512 and 1024, Titan Multimodal Embeddings 256, 384 and 1024,
+    /// which is checked when the first request is built.
+    pub fn with_dimension(mut self, dimension: u32) -> Self {
+        self.dimension = Some(dimension);
+        self
+    }
+
+    /// Whether Titan Text Embeddings V2 scales embeddings to unit length. The other models
+    /// cannot, which is reported when the first request is built.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = Some(normalize);
+        self
+    }
+
+    pub fn model(&self) -> M {
+        self.model
+    }
+}
+
+impl<M: Display> Display for TitanEmbedder<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.model)
+    }
+}
+
+impl<M: BedrockModel> BedrockModel for TitanEmbedder<M> {
+    const PROVIDER: &'static str = M::PROVIDER;
+    type Params = M::Params;
+    type Response = M::Response;
+
+    fn validate_params(&self, params: &M::Params) -> Result<()> {
+        self.model.validate_params(params)
+    }
+}
+
+/// A Titan embeddings model, each of which takes its own params but returns a
+/// [`TitanEmbeddingResponse`].
+pub trait TitanEmbeddingApi:
+    BedrockModel<Response = TitanEmbeddingResponse> + Copy + Into<BaseModel>
+{
+    /// The request body embedding `text`, with the settings of a [`TitanEmbedder`].
+    fn embedding_params(
+        &self,
+        text: &str,
+        dimension: Option<u32>,
+        normalize: Option<bool>,
+    ) -> Result<Self::Params>;
+}
+
+fn reject_normalize(model: &impl BedrockModel, normalize: Option<bool>) -> Result<()> {
+    match normalize {
+        Some(_) => Err(StoneMasonError::invalid_params(format!(
+            "{} does not support normalize",
+            model.model_id()
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl TitanEmbeddingApi for TitanEmbeddingModel {
+    fn embedding_params(
+        &self,
+        text: &str,
+        dimension: Option<u32>,
+        normalize: Option<bool>,
+    ) -> Result<TitanEmbeddingParams> {
+        reject_normalize(self, normalize)?;
+        let default = catalog_dimension(*self);
+        if let Some(dimension) = dimension.filter(|&d| d as usize != default) {
+            return Err(StoneMasonError::invalid_params(format!(
+                "{} only returns embeddings of length {default}, not {dimension}",
+                self.model_id()
+            )));
+        }
+        Ok(TitanEmbeddingParamsBuilder::default()
+            .input_text(text.to_string())
+            .build()?)
+    }
+}
+
+impl TitanEmbeddingApi for TitanEmbeddingV2Model {
+    fn embedding_params(
+        &self,
+        text: &str,
+        dimension: Option<u32>,
+        normalize: Option<bool>,
+    ) -> Result<TitanEmbeddingV2Params> {
+        let mut builder = TitanEmbeddingV2ParamsBuilder::default();
+        builder.input_text(text);
+        if let Some(dimension) = dimension {
+            builder.dimensions(dimension);
+        }
+        if let Some(normalize) = normalize {
+            builder.normalize(normalize);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+impl TitanEmbeddingApi for TitanMultimodalEmbeddingModel {
+    fn embedding_params(
+        &self,
+        text: &str,
+        dimension: Option<u32>,
+        normalize: Option<bool>,
+    ) -> Result<TitanMultimodalEmbeddingParams> {
+        reject_normalize(self, normalize)?;
+        let mut builder = TitanMultimodalEmbeddingParamsBuilder::default();
+        builder.input_text(text);
+        if let Some(dimension) = dimension {
+            builder.embedding_config(EmbeddingConfig {
+                output_embedding_length: dimension,
+            });
+        }
+        Ok(builder.build()?)
+    }
+}
+
+impl<M: TitanEmbeddingApi> Embedder for TitanEmbedder<M> {
+    fn max_batch_size(&self) -> usize {
+        1
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+            .map_or_else(|| catalog_dimension(self.model), |d| d as usize)
+    }
+
+    fn batch_params(&self, texts: &[&str]) -> Result<M::Params> {
+        let [text] = texts else {
+            return Err(StoneMasonError::invalid_params(format!(
+                "{} embeds one text per request, got {}",
+                self.model_id(),
+                texts.len()
+            )));
+        };
+        self.model
+            .embedding_params(text, self.dimension, self.normalize)
+    }
+
+    fn batch_embeddings(&self, response: TitanEmbeddingResponse) -> Result<Vec<Vec<f32>>> {
+        Ok(vec![response.into_embedding(self.dimension())?])
+    }
+}
+
+/// The most texts a Cohere Embed request can carry.
+const COHERE_MAX_BATCH_SIZE: usize = 96;
+
+/// Embeds up to 96 texts per request with either of the Cohere Embed models.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CohereEmbedder {
+    model: CohereEmbedModel,
+    input_type: InputType,
+    truncate: Option<Truncate>,
+}
+
+impl CohereEmbedder {
+    pub fn new(model: CohereEmbedModel, input_type: InputType) -> Self {
+        CohereEmbedder {
+            model,
+            input_type,
+            truncate: None,
+        }
+    }
+
+    /// How texts longer than the model's maximum input are handled.
+    pub fn truncate(mut self, truncate: Truncate) -> Self {
+        self.truncate = Some(truncate);
+        self
+    }
+
+    pub fn model(&self) -> CohereEmbedModel {
+        self.model
+    }
+}
+
+impl Display for CohereEmbedder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.model)
+    }
+}
+
+impl BedrockModel for CohereEmbedder {
+    const PROVIDER: &'static str = CohereModel::PROVIDER;
+    type Params = CohereEmbedParams;
+    type Response = CohereEmbedResponse;
+}
+
+impl Embedder for CohereEmbedder {
+    fn max_batch_size(&self) -> usize {
+        COHERE_MAX_BATCH_SIZE
+    }
+
+    fn dimension(&self) -> usize {
+        catalog_dimension(self.model)
+    }
+
+    fn batch_params(&self, texts: &[&str]) -> Result<CohereEmbedParams> {
+        let mut builder = CohereEmbedParamsBuilder::default();
+        builder
+            .texts(texts.iter().map(|text| text.to_string()).collect())
+            .input_type(self.input_type);
+        if let Some(truncate) = self.truncate {
+            builder.truncate(truncate);
+        }
+        Ok(builder.build()?)
+    }
+
+    fn batch_embeddings(&self, response: CohereEmbedResponse) -> Result<Vec<Vec<f32>>> {
+        Ok(response.embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+    use crate::FromModelOutput;
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::task::Poll;
+
+    /// Completes on the second poll, so other batches get polled while this one is in flight.
+    async fn yield_once() {
+        let mut yielded = false;
+        futures::future::poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[test]
+    fn test_embed_batches_keeps_order_and_limits_concurrency() {
+        let texts: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let batch_sizes = Mutex::new(Vec::new());
+
+        let vectors = block_on(embed_batches(&texts, 3, 2, 1, |batch| {
+            let (in_flight, max_in_flight, batch_sizes) =
+                (&in_flight, &max_in_flight, &batch_sizes);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                batch_sizes.lock().unwrap().push(batch.len());
+                yield_once().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(batch
+                    .iter()
+                    .map(|text| vec![text.parse::<f32>().unwrap()])
+                    .collect())
+            }
+        }))
+        .unwrap();
+
+        let expected: Vec<Vec<f32>> = (0..10).map(|i| vec![i as f32]).collect();
+        assert_eq!(vectors, expected);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+        assert_eq!(*batch_sizes.lock().unwrap(), vec![3, 3, 3, 1]);
+    }
+
+    #[test]
+    fn test_embed_batches_checks_responses() {
+        let texts = ["a", "b"];
+
+        let err = block_on(embed_batches(&texts, 2, 1, 3, |_| async {
+            Ok(vec![vec![0.0; 3]])
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected response: expected 2 embeddings, got 1"
+        );
+
+        let err = block_on(embed_batches(&texts, 1, 1, 3, |_| async {
+            Ok(vec![vec![0.0; 4]])
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected response: expected an embedding of length 3, got 4"
+        );
+
+        let vectors = block_on(embed_batches(&[], 1, 1, 3, |_| async {
+            unreachable!("no batches to embed")
+        }))
+        .unwrap();
+        assert!(vectors.is_empty());
+    }
+
+    #[test]
+    fn test_titan_embedder() {
+        let embedder = TitanEmbedder::new(TitanEmbeddingModel::TitanEmbeddingsTextV1);
+        assert_eq!(embedder.max_batch_size(), 1);
+        assert_eq!(embedder.dimension(), 1536);
+        assert_eq!(embedder.model_id(), "amazon.titan-embed-text-v1");
+
+        let params = embedder.batch_params(&["Hello world"]).unwrap();
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"inputText":"Hello world"}"#
+        );
+        assert!(embedder.batch_params(&["a", "b"]).is_err());
+        assert!(embedder.with_dimension(256).batch_params(&["a"]).is_err());
+        assert_eq!(
+            embedder
+                .normalize(true)
+                .batch_params(&["a"])
+                .unwrap_err()
+                .to_string(),
+            "invalid params: amazon.titan-embed-text-v1 does not support normalize"
+        );
+
+        let embedder =
+            TitanEmbedder::new(TitanMultimodalEmbeddingModel::TitanMultimodalEmbeddingsV1)
+                .with_dimension(384);
+        assert_eq!(embedder.dimension(), 384);
+        let params = embedder.batch_params(&["a red shoe"]).unwrap();
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"inputText":"a red shoe","embeddingConfig":{"outputEmbeddingLength":384}}"#
+        );
+
+        let output = model_output(include_str!(
+            "../tests/fixtures/amazon/titan_embedding.json"
+        ));
+        let res = TitanEmbeddingResponse::from_model_output(&output).unwrap();
+        assert_eq!(
+            embedder.batch_embeddings(res).unwrap_err().to_string(),
+            "unexpected response: expected an embedding of length 384, got 4"
+        );
+    }
+
+    #[test]
+    fn test_cohere_embedder() {
+        let embedder =
+            CohereEmbedder::new(CohereEmbedModel::EmbedEnglishV3, InputType::SearchQuery)
+                .truncate(Truncate::End);
+        assert_eq!(embedder.max_batch_size(), 96);
+        assert_eq!(embedder.dimension(), 1024);
+
+        let params = embedder.batch_params(&["first", "second"]).unwrap();
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"texts":["first","second"],"input_type":"search_query","truncate":"END"}"#
+        );
+
+        let output = model_output(include_str!("../tests/fixtures/cohere/embed.json"));
+        let res = CohereEmbedResponse::from_model_output(&output).unwrap();
+        assert_eq!(embedder.batch_embeddings(res).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_catalog_has_every_embedder_dimension() {
+        let models = TitanEmbeddingModel::ALL
+            .into_iter()
+            .map(BaseModel::from)
+            .chain(TitanEmbeddingV2Model::ALL.into_iter().map(BaseModel::from))
+            .chain(
+                TitanMultimodalEmbeddingModel::ALL
+                    .into_iter()
+                    .map(BaseModel::from),
+            )
+            .chain(CohereEmbedModel::ALL.into_iter().map(BaseModel::from));
+        for model in models {
+            assert!(
+                model.capabilities().embedding_dimension.is_some(),
+                "{model}"
+            );
+        }
+    }
+}
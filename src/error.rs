@@ -0,0 +1,318 @@
+use crate::ai21::AI21InferenceParametersBuilderError;
+use crate::amazon::{
+    AmazonParamsBuilderError, ImageGenerationConfigBuilderError, ImageVariationParamsBuilderError,
+    InPaintingParamsBuilderError, OutPaintingParamsBuilderError, TextToImageParamsBuilderError,
+    TitanConfigError, TitanEmbeddingParamsBuilderError, TitanEmbeddingV2ParamsBuilderError,
+    TitanImageParamsBuilderError, TitanMultimodalEmbeddingParamsBuilderError,
+};
+use crate::anthropic::messages::MessagesParamsBuilderError;
+use crate::anthropic::AnthropicParamsBuilderError;
+use crate::cohere::{
+    CohereChatParamsBuilderError, CohereEmbedParamsBuilderError, CohereParamsBuilderError,
+};
+use crate::completion::CompletionRequestBuilderError;
+use crate::meta::MetaParamsBuilderError;
+use crate::mistral::MistralParamsBuilderError;
+use crate::stability::{StabilityParamsBuilderError, TextPromptBuilderError};
+use aws_sdk_bedrockruntime::error::SdkError;
+use aws_sdk_bedrockruntime::operation::invoke_model::InvokeModelError;
+use aws_sdk_bedrockruntime::operation::invoke_model_with_response_stream::InvokeModelWithResponseStreamError;
+use aws_sdk_bedrockruntime::types::error::{
+    AccessDeniedException, InternalServerException, ModelErrorException, ModelNotReadyException,
+    ModelStreamErrorException, ModelTimeoutException, ResourceNotFoundException,
+    ResponseStreamError, ServiceQuotaExceededException, ThrottlingException, ValidationException,
+};
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub type Result<T, E = StoneMasonError> = std::result::Result<T, E>;
+
+#[derive(Error, Debug)]
+pub enum StoneMasonError {
+    /// The string is not the id of any model known to this crate.
+    #[error("unknown model id {0:?}")]
+    UnknownModelId(String),
+
+    /// An ARN or inference profile id which cannot identify a Bedrock model.
+    #[error("invalid model identifier: {0}")]
+    InvalidModelIdentifier(String),
+
+    /// The model cannot be used for the requested kind of invocation.
+    #[error("{model} does not support {operation}")]
+    UnsupportedOperation {
+        model: String,
+        operation: &'static str,
+    },
+
+    /// A conversation could not be rendered into a prompt.
+    #[error("{0}")]
+    InvalidConversation(String),
+
+    /// Params failed validation when built, or when checked against the model's limits. The
+    /// source is the builder's own error, e.g. a [`TitanConfigError`], which can be recovered
+    /// with `downcast_ref`.
+    #[error("invalid params: {source}")]
+    InvalidParams {
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("failed to serialize request body")]
+    Serialization(#[source] serde_json::Error),
+
+    #[error("response body is not valid UTF-8")]
+    InvalidUtf8 {
+        #[source]
+        source: std::str::Utf8Error,
+        body: Vec<u8>,
+    },
+
+    #[error("failed to deserialize response body: {body}")]
+    Deserialization {
+        #[source]
+        source: serde_json::Error,
+        body: String,
+    },
+
+    /// The response deserialized, but did not contain what was expected.
+    #[error("unexpected response: {0}")]
+    UnexpectedResponse(String),
+
+    /// Embeddings from one model were mixed with those of another, or of another length.
+    #[error("incompatible embeddings: expected {expected}, got {found}")]
+    IncompatibleEmbeddings { expected: String, found: String },
+
+    /// A vector index file is truncated or not in either of the index formats.
+    #[error("invalid index file: {0}")]
+    InvalidIndexFile(String),
+
+    #[error("failed to access {}", path.display())]
+    Io {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    // Service errors are boxed, as the SDK's exception types would otherwise make every
+    // `Result` in the crate several hundred bytes large.
+    #[error(transparent)]
+    Throttling(Box<ThrottlingException>),
+
+    #[error(transparent)]
+    Validation(Box<ValidationException>),
+
+    #[error(transparent)]
+    ModelTimeout(Box<ModelTimeoutException>),
+
+    #[error(transparent)]
+    ModelNotReady(Box<ModelNotReadyException>),
+
+    #[error(transparent)]
+    AccessDenied(Box<AccessDeniedException>),
+
+    #[error(transparent)]
+    ServiceQuotaExceeded(Box<ServiceQuotaExceededException>),
+
+    #[error(transparent)]
+    ResourceNotFound(Box<ResourceNotFoundException>),
+
+    #[error(transparent)]
+    InternalServer(Box<InternalServerException>),
+
+    #[error(transparent)]
+    ModelError(Box<ModelErrorException>),
+
+    #[error(transparent)]
+    ModelStreamError(Box<ModelStreamErrorException>),
+
+    /// Any other failure from the SDK, such as a dispatch failure or an unmodeled service error.
+    #[error("Bedrock request failed")]
+    Sdk(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl StoneMasonError {
+    pub(crate) fn invalid_params(message: impl Into<String>) -> Self {
+        StoneMasonError::InvalidParams {
+            source: message.into().into(),
+        }
+    }
+
+    pub(crate) fn deserialization(source: serde_json::Error, body: &[u8]) -> Self {
+        StoneMasonError::Deserialization {
+            source,
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+
+    /// Whether the request may succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            StoneMasonError::Throttling(_)
+                | StoneMasonError::ModelTimeout(_)
+                | StoneMasonError::ModelNotReady(_)
+                | StoneMasonError::ServiceQuotaExceeded(_)
+                | StoneMasonError::InternalServer(_)
+        )
+    }
+}
+
+impl<R> From<SdkError<InvokeModelError, R>> for StoneMasonError
+where
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    fn from(e: SdkError<InvokeModelError, R>) -> Self {
+        match e.into_service_error() {
+            InvokeModelError::ThrottlingException(e) => StoneMasonError::Throttling(Box::new(e)),
+            InvokeModelError::ValidationException(e) => StoneMasonError::Validation(Box::new(e)),
+            InvokeModelError::ModelTimeoutException(e) => {
+                StoneMasonError::ModelTimeout(Box::new(e))
+            }
+            InvokeModelError::ModelNotReadyException(e) => {
+                StoneMasonError::ModelNotReady(Box::new(e))
+            }
+            InvokeModelError::AccessDeniedException(e) => {
+                StoneMasonError::AccessDenied(Box::new(e))
+            }
+            InvokeModelError::ServiceQuotaExceededException(e) => {
+                StoneMasonError::ServiceQuotaExceeded(Box::new(e))
+            }
+            InvokeModelError::ResourceNotFoundException(e) => {
+                StoneMasonError::ResourceNotFound(Box::new(e))
+            }
+            InvokeModelError::InternalServerException(e) => {
+                StoneMasonError::InternalServer(Box::new(e))
+            }
+            InvokeModelError::ModelErrorException(e) => StoneMasonError::ModelError(Box::new(e)),
+            e => StoneMasonError::Sdk(Box::new(e)),
+        }
+    }
+}
+
+impl<R> From<SdkError<InvokeModelWithResponseStreamError, R>> for StoneMasonError
+where
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    fn from(e: SdkError<InvokeModelWithResponseStreamError, R>) -> Self {
+        use InvokeModelWithResponseStreamError as E;
+        match e.into_service_error() {
+            E::ThrottlingException(e) => StoneMasonError::Throttling(Box::new(e)),
+            E::ValidationException(e) => StoneMasonError::Validation(Box::new(e)),
+            E::ModelTimeoutException(e) => StoneMasonError::ModelTimeout(Box::new(e)),
+            E::ModelNotReadyException(e) => StoneMasonError::ModelNotReady(Box::new(e)),
+            E::AccessDeniedException(e) => StoneMasonError::AccessDenied(Box::new(e)),
+            E::ServiceQuotaExceededException(e) => {
+                StoneMasonError::ServiceQuotaExceeded(Box::new(e))
+            }
+            E::ResourceNotFoundException(e) => StoneMasonError::ResourceNotFound(Box::new(e)),
+            E::InternalServerException(e) => StoneMasonError::InternalServer(Box::new(e)),
+            E::ModelErrorException(e) => StoneMasonError::ModelError(Box::new(e)),
+            E::ModelStreamErrorException(e) => StoneMasonError::ModelStreamError(Box::new(e)),
+            e => StoneMasonError::Sdk(Box::new(e)),
+        }
+    }
+}
+
+impl<R> From<SdkError<ResponseStreamError, R>> for StoneMasonError
+where
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    fn from(e: SdkError<ResponseStreamError, R>) -> Self {
+        match e.into_service_error() {
+            ResponseStreamError::ThrottlingException(e) => StoneMasonError::Throttling(Box::new(e)),
+            ResponseStreamError::ValidationException(e) => StoneMasonError::Validation(Box::new(e)),
+            ResponseStreamError::ModelTimeoutException(e) => {
+                StoneMasonError::ModelTimeout(Box::new(e))
+            }
+            ResponseStreamError::InternalServerException(e) => {
+                StoneMasonError::InternalServer(Box::new(e))
+            }
+            ResponseStreamError::ModelStreamErrorException(e) => {
+                StoneMasonError::ModelStreamError(Box::new(e))
+            }
+            e => StoneMasonError::Sdk(Box::new(e)),
+        }
+    }
+}
+
+macro_rules! impl_from_invalid_params {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for StoneMasonError {
+                fn from(e: $error) -> Self {
+                    StoneMasonError::InvalidParams { source: Box::new(e) }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_invalid_params! {
+    AI21InferenceParametersBuilderError,
+    AmazonParamsBuilderError,
+    AnthropicParamsBuilderError,
+    CohereChatParamsBuilderError,
+    CohereEmbedParamsBuilderError,
+    CohereParamsBuilderError,
+    CompletionRequestBuilderError,
+    ImageGenerationConfigBuilderError,
+    ImageVariationParamsBuilderError,
+    InPaintingParamsBuilderError,
+    MessagesParamsBuilderError,
+    MetaParamsBuilderError,
+    MistralParamsBuilderError,
+    OutPaintingParamsBuilderError,
+    StabilityParamsBuilderError,
+    TextPromptBuilderError,
+    TextToImageParamsBuilderError,
+    TitanConfigError,
+    TitanEmbeddingParamsBuilderError,
+    TitanEmbeddingV2ParamsBuilderError,
+    TitanImageParamsBuilderError,
+    TitanMultimodalEmbeddingParamsBuilderError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_errors_are_distinguishable() {
+        let err: StoneMasonError = SdkError::<_, ()>::service_error(
+            InvokeModelError::ThrottlingException(
+                ThrottlingException::builder()
+                    .message("Too many requests")
+                    .build(),
+            ),
+            (),
+        )
+        .into();
+        assert!(matches!(err, StoneMasonError::Throttling(_)));
+        assert!(err.is_retryable());
+
+        let err: StoneMasonError = SdkError::<_, ()>::service_error(
+            InvokeModelError::AccessDeniedException(AccessDeniedException::builder().build()),
+            (),
+        )
+        .into();
+        assert!(matches!(err, StoneMasonError::AccessDenied(_)));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_invalid_params_keeps_source() {
+        let err: StoneMasonError = TitanConfigError::TopP(1.5).into();
+        assert_eq!(
+            err.to_string(),
+            "invalid params: topP must be between 0 and 1, got 1.5"
+        );
+
+        let StoneMasonError::InvalidParams { source } = err else {
+            panic!("expected InvalidParams, got {err:?}");
+        };
+        assert_eq!(
+            source.downcast_ref::<TitanConfigError>(),
+            Some(&TitanConfigError::TopP(1.5))
+        );
+    }
+}
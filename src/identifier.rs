@@ -0,0 +1,388 @@
+use crate::stream::StreamingModel;
+use crate::{BaseModel, BedrockModel, Result, StoneMasonError};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Identifies what to invoke: a foundation model, or a deployment of one. Deployments keep the
+/// foundation model they are based on, which determines the params and response types.
+///
+/// ```
+/// use stone_mason::amazon::TitanTextModel;
+/// use stone_mason::anthropic::ClaudeTextModel;
+/// use stone_mason::identifier::ModelIdentifier;
+/// use stone_mason::BedrockModel;
+///
+/// let model = ModelIdentifier::custom_model(
+///     "arn:aws:bedrock:us-east-1:123456789012:custom-model/amazon.titan-text-express-v1:0:8k/x1y2z3",
+///     TitanTextModel::TitanTextExpressV1,
+/// )
+/// .unwrap();
+/// assert_eq!(model.base(), &TitanTextModel::TitanTextExpressV1);
+///
+/// let model = ModelIdentifier::inference_profile("us", ClaudeTextModel::ClaudeV2).unwrap();
+/// assert_eq!(model.model_id(), "us.anthropic.claude-v2");
+/// ```
+///
+/// The base defaults to [`BaseModel`], for when the model is only known at runtime, e.g. to
+/// pass to [`crate::StoneMason::complete`]. Foundation model and inference profile ids parse
+/// into any base; ARNs do not name their base model, so must be built with
+/// [`Self::provisioned_throughput`] or [`Self::custom_model`].
+///
+/// ```
+/// use stone_mason::anthropic::AnthropicModel;
+/// use stone_mason::identifier::ModelIdentifier;
+/// use stone_mason::BaseModel;
+///
+/// let model: ModelIdentifier = "us.anthropic.claude-v2".parse().unwrap();
+/// assert_eq!(model.base(), &BaseModel::Anthropic(AnthropicModel::ClaudeV2));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ModelIdentifier<M = BaseModel> {
+    /// A foundation model, invoked by its model id.
+    Foundation(M),
+
+    /// Provisioned throughput for a foundation or custom model, invoked by its ARN.
+    ProvisionedThroughput { arn: String, base: M },
+
+    /// A fine-tuned model, invoked by its ARN.
+    CustomModel { arn: String, base: M },
+
+    /// A cross-region inference profile, e.g. `us.anthropic.claude-v2`.
+    InferenceProfile { id: String, base: M },
+}
+
+impl<M> ModelIdentifier<M> {
+    /// Checks that `arn` is the ARN of a Bedrock provisioned model.
+    pub fn provisioned_throughput(arn: impl Into<String>, base: M) -> Result<Self> {
+        let arn = arn.into();
+        check_arn(&arn, "provisioned-model/")?;
+        Ok(ModelIdentifier::ProvisionedThroughput { arn, base })
+    }
+
+    /// Checks that `arn` is the ARN of a Bedrock custom model.
+    pub fn custom_model(arn: impl Into<String>, base: M) -> Result<Self> {
+        let arn = arn.into();
+        check_arn(&arn, "custom-model/")?;
+        Ok(ModelIdentifier::CustomModel { arn, base })
+    }
+
+    /// The foundation model this identifier invokes, or is based on.
+    pub fn base(&self) -> &M {
+        match self {
+            ModelIdentifier::Foundation(base)
+            | ModelIdentifier::ProvisionedThroughput { base, .. }
+            | ModelIdentifier::CustomModel { base, .. }
+            | ModelIdentifier::InferenceProfile { base, .. } => base,
+        }
+    }
+
+    /// The same deployment, with `base` in place of the current base model. Used to narrow a
+    /// [`BaseModel`] down to the model type of the API it is invoked with.
+    pub fn with_base<N>(&self, base: N) -> ModelIdentifier<N> {
+        match self {
+            ModelIdentifier::Foundation(_) => ModelIdentifier::Foundation(base),
+            ModelIdentifier::ProvisionedThroughput { arn, .. } => {
+                ModelIdentifier::ProvisionedThroughput {
+                    arn: arn.clone(),
+                    base,
+                }
+            }
+            ModelIdentifier::CustomModel { arn, .. } => ModelIdentifier::CustomModel {
+                arn: arn.clone(),
+                base,
+            },
+            ModelIdentifier::InferenceProfile { id, .. } => ModelIdentifier::InferenceProfile {
+                id: id.clone(),
+                base,
+            },
+        }
+    }
+}
+
+impl<M: Copy + Into<BaseModel>> ModelIdentifier<M> {
+    /// The inference profile routing `base` across the regions of `region`, e.g. `us` or `eu`.
+    pub fn inference_profile(region: &str, base: M) -> Result<Self> {
+        if region.is_empty() || !region.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
+            return Err(StoneMasonError::InvalidModelIdentifier(format!(
+                "{region:?} is not an inference profile region"
+            )));
+        }
+        let id = format!("{region}.{}", base.into().model_id());
+        Ok(ModelIdentifier::InferenceProfile { id, base })
+    }
+
+    /// The model id or ARN to invoke.
+    pub fn model_id(&self) -> String {
+        match self {
+            ModelIdentifier::Foundation(model) => (*model).into().model_id(),
+            ModelIdentifier::ProvisionedThroughput { arn, .. }
+            | ModelIdentifier::CustomModel { arn, .. } => arn.clone(),
+            ModelIdentifier::InferenceProfile { id, .. } => id.clone(),
+        }
+    }
+}
+
+fn check_arn(arn: &str, resource_prefix: &str) -> Result<()> {
+    // arn:partition:bedrock:region:account-id:resource, where the resource may contain colons
+    let parts: Vec<&str> = arn.splitn(6, ':').collect();
+    match parts[..] {
+        ["arn", partition, "bedrock", region, account, resource]
+            if !partition.is_empty()
+                && !region.is_empty()
+                && !account.is_empty()
+                && resource.len() > resource_prefix.len()
+                && resource.starts_with(resource_prefix) =>
+        {
+            Ok(())
+        }
+        _ => Err(StoneMasonError::InvalidModelIdentifier(format!(
+            "{arn:?} is not a Bedrock {} ARN",
+            resource_prefix.trim_end_matches('/')
+        ))),
+    }
+}
+
+impl<M: Copy + Into<BaseModel>> From<M> for ModelIdentifier<M> {
+    fn from(model: M) -> Self {
+        ModelIdentifier::Foundation(model)
+    }
+}
+
+impl<M: Copy + Into<BaseModel>> Display for ModelIdentifier<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.model_id())
+    }
+}
+
+/// Parses a foundation model id, e.g. `anthropic.claude-v2`, or an inference profile id, e.g.
+/// `us.anthropic.claude-v2`.
+impl<M> FromStr for ModelIdentifier<M>
+where
+    M: Copy + Into<BaseModel> + FromStr<Err = StoneMasonError>,
+{
+    type Err = StoneMasonError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("arn:") {
+            return Err(StoneMasonError::InvalidModelIdentifier(format!(
+                "the base model of {s:?} cannot be inferred from the ARN"
+            )));
+        }
+        let err = match s.parse::<M>() {
+            Ok(base) if ModelIdentifier::Foundation(base).model_id() == s => {
+                return Ok(ModelIdentifier::Foundation(base))
+            }
+            Ok(_) => StoneMasonError::UnknownModelId(s.to_string()),
+            Err(err) => err,
+        };
+        let profile = s.split_once('.').and_then(|(region, id)| {
+            let base = id.parse::<M>().ok()?;
+            ModelIdentifier::inference_profile(region, base).ok()
+        });
+        match profile {
+            Some(profile) if profile.model_id() == s => Ok(profile),
+            _ => Err(err),
+        }
+    }
+}
+
+/// Foundation model and inference profile ids are serialized as plain strings, and ARNs along
+/// with their base model, e.g. `{"arn": "arn:aws:bedrock:...", "base": "anthropic.claude-v2"}`.
+impl<M: Copy + Into<BaseModel> + Serialize> Serialize for ModelIdentifier<M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            ModelIdentifier::Foundation(_) | ModelIdentifier::InferenceProfile { .. } => {
+                serializer.serialize_str(&self.model_id())
+            }
+            ModelIdentifier::ProvisionedThroughput { arn, base }
+            | ModelIdentifier::CustomModel { arn, base } => {
+                let mut state = serializer.serialize_struct("ModelIdentifier", 2)?;
+                state.serialize_field("arn", arn)?;
+                state.serialize_field("base", base)?;
+                state.end()
+            }
+        }
+    }
+}
+
+impl<'de, M> Deserialize<'de> for ModelIdentifier<M>
+where
+    M: Copy + Into<BaseModel> + FromStr<Err = StoneMasonError> + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr<M> {
+            Id(String),
+            Arn { arn: String, base: M },
+        }
+
+        match Repr::<M>::deserialize(deserializer)? {
+            Repr::Id(id) => id.parse(),
+            Repr::Arn { arn, base } if arn.contains(":custom-model/") => {
+                ModelIdentifier::custom_model(arn, base)
+            }
+            Repr::Arn { arn, base } => ModelIdentifier::provisioned_throughput(arn, base),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<M: BedrockModel + Copy + Into<BaseModel>> BedrockModel for ModelIdentifier<M> {
+    const PROVIDER: &'static str = M::PROVIDER;
+    type Params = M::Params;
+    type Response = M::Response;
+
+    fn model_id(&self) -> String {
+        ModelIdentifier::model_id(self)
+    }
+
+    fn validate_params(&self, params: &Self::Params) -> Result<()> {
+        self.base().validate_params(params)
+    }
+}
+
+impl<M: StreamingModel + Copy + Into<BaseModel>> StreamingModel for ModelIdentifier<M> {
+    type Chunk = M::Chunk;
+    const STREAM_FIELD: bool = M::STREAM_FIELD;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amazon::{
+        AmazonModel, AmazonParamsBuilder, TextGenerationConfigBuilder, TitanTextModel,
+    };
+    use crate::anthropic::{AnthropicModel, ClaudeTextModel};
+
+    #[test]
+    fn test_model_id() {
+        let model = ModelIdentifier::from(ClaudeTextModel::ClaudeV2);
+        assert_eq!(model.model_id(), "anthropic.claude-v2");
+        assert_eq!(model.to_string(), "anthropic.claude-v2");
+
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/abc123def456";
+        let model =
+            ModelIdentifier::provisioned_throughput(arn, ClaudeTextModel::ClaudeInstantV1).unwrap();
+        assert_eq!(model.model_id(), arn);
+        assert_eq!(model.base(), &ClaudeTextModel::ClaudeInstantV1);
+
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:custom-model/amazon.titan-text-express-v1:0:8k/x1y2z3";
+        let model = ModelIdentifier::custom_model(arn, TitanTextModel::TitanTextExpressV1).unwrap();
+        assert_eq!(model.model_id(), arn);
+
+        let model = ModelIdentifier::inference_profile("eu", ClaudeTextModel::ClaudeV2).unwrap();
+        assert_eq!(model.model_id(), "eu.anthropic.claude-v2");
+    }
+
+    #[test]
+    fn test_invalid_identifiers() {
+        let custom_arn = "arn:aws:bedrock:us-east-1:123456789012:custom-model/amazon.titan-text-express-v1:0:8k/x1y2z3";
+        for arn in [
+            custom_arn,
+            "arn:aws:s3:us-east-1:123456789012:provisioned-model/abc",
+            "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/",
+            "anthropic.claude-v2",
+        ] {
+            let err = ModelIdentifier::provisioned_throughput(arn, ClaudeTextModel::ClaudeV2)
+                .unwrap_err();
+            assert!(matches!(err, StoneMasonError::InvalidModelIdentifier(_)));
+        }
+
+        let err = ModelIdentifier::inference_profile("us.", ClaudeTextModel::ClaudeV2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid model identifier: \"us.\" is not an inference profile region"
+        );
+    }
+
+    #[test]
+    fn test_validates_params_against_base_model() {
+        let params = AmazonParamsBuilder::default()
+            .input_text("Hello".to_string())
+            .text_generation_config(
+                TextGenerationConfigBuilder::default()
+                    .max_token_count(8192)
+                    .stop_sequences(vec![])
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/abc123def456";
+
+        let model =
+            ModelIdentifier::provisioned_throughput(arn, TitanTextModel::TitanTextLiteV1).unwrap();
+        assert!(model.validate_params(&params.clone()).is_err());
+
+        let model =
+            ModelIdentifier::provisioned_throughput(arn, TitanTextModel::TitanTextExpressV1)
+                .unwrap();
+        assert!(model.validate_params(&params).is_ok());
+    }
+
+    #[test]
+    fn test_parse() {
+        let model: ModelIdentifier = "anthropic.claude-v2".parse().unwrap();
+        assert_eq!(
+            model,
+            ModelIdentifier::Foundation(BaseModel::Anthropic(AnthropicModel::ClaudeV2))
+        );
+
+        let model: ModelIdentifier<ClaudeTextModel> = "us.anthropic.claude-v2".parse().unwrap();
+        assert_eq!(
+            model,
+            ModelIdentifier::inference_profile("us", ClaudeTextModel::ClaudeV2).unwrap()
+        );
+
+        let err = "claude-v2".parse::<ModelIdentifier>().unwrap_err();
+        assert!(matches!(err, StoneMasonError::UnknownModelId(_)));
+
+        let err = "anthropic.claude-3-haiku-20240307-v1:0"
+            .parse::<ModelIdentifier<ClaudeTextModel>>()
+            .unwrap_err();
+        assert!(matches!(err, StoneMasonError::UnsupportedOperation { .. }));
+
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/abc123def456";
+        let err = arn.parse::<ModelIdentifier>().unwrap_err();
+        assert!(matches!(err, StoneMasonError::InvalidModelIdentifier(_)));
+    }
+
+    #[test]
+    fn test_serde() {
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:custom-model/amazon.titan-text-express-v1:0:8k/x1y2z3";
+        let base = BaseModel::from(AmazonModel::TitanTextExpressV1);
+        for (model, json) in [
+            (
+                ModelIdentifier::Foundation(base),
+                serde_json::json!("amazon.titan-text-express-v1"),
+            ),
+            (
+                ModelIdentifier::inference_profile("us", base).unwrap(),
+                serde_json::json!("us.amazon.titan-text-express-v1"),
+            ),
+            (
+                ModelIdentifier::custom_model(arn, base).unwrap(),
+                serde_json::json!({"arn": arn, "base": "amazon.titan-text-express-v1"}),
+            ),
+        ] {
+            assert_eq!(serde_json::to_value(&model).unwrap(), json);
+            assert_eq!(
+                serde_json::from_value::<ModelIdentifier>(json).unwrap(),
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_base() {
+        let model: ModelIdentifier = "eu.anthropic.claude-v2".parse().unwrap();
+        let BaseModel::Anthropic(base) = *model.base() else {
+            panic!("expected an Anthropic model, got {}", model.base());
+        };
+        let model = model.with_base(ClaudeTextModel::try_from(base).unwrap());
+        assert_eq!(BedrockModel::model_id(&model), "eu.anthropic.claude-v2");
+    }
+}
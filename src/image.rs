@@ -0,0 +1,117 @@
+//! Images sent to and returned by the image generation and multimodal models.
+
+use crate::{Result, StoneMasonError};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+
+/// Raw image bytes, sent to and received from Bedrock as a base64 string.
+#[derive(Clone, PartialEq)]
+pub struct Image(pub Vec<u8>);
+
+impl Image {
+    /// Reads the image at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| StoneMasonError::Io {
+            source,
+            path: path.to_path_buf(),
+        })?;
+        Ok(Image(bytes))
+    }
+
+    /// Writes the image to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write(path.as_ref(), &self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Image {
+    fn from(bytes: Vec<u8>) -> Self {
+        Image(bytes)
+    }
+}
+
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image(<{} bytes>)", self.0.len())
+    }
+}
+
+impl Serialize for Image {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Image {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        decode_base64(deserializer).map(Image)
+    }
+}
+
+pub(crate) fn decode_base64<'de, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = String::deserialize(deserializer)?;
+    STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+}
+
+pub(crate) fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes).map_err(|source| StoneMasonError::Io {
+        source,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Writes each `(file_name, bytes)` pair into `dir`, returning the paths written.
+pub(crate) fn save_all<'a>(
+    dir: &Path,
+    images: impl IntoIterator<Item = (String, &'a [u8])>,
+) -> Result<Vec<PathBuf>> {
+    images
+        .into_iter()
+        .map(|(file_name, bytes)| {
+            let path = dir.join(file_name);
+            write(&path, bytes)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_round_trip() {
+        let image = Image(vec![0x89, 0x50, 0x4e, 0x47]);
+        let json = serde_json::to_string(&image).unwrap();
+        assert_eq!(json, r#""iVBORw==""#);
+        assert_eq!(serde_json::from_str::<Image>(&json).unwrap(), image);
+        assert!(serde_json::from_str::<Image>(r#""not base64!""#).is_err());
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let dir = std::env::temp_dir().join(format!("stone-mason-image-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let image = Image(b"\x89PNG\r\n\x1a\n".to_vec());
+        let path = dir.join("image.png");
+        image.save(&path).unwrap();
+        assert_eq!(Image::open(&path).unwrap(), image);
+
+        let err = Image::open(dir.join("missing.png")).unwrap_err();
+        assert!(matches!(err, StoneMasonError::Io { .. }));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
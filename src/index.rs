@@ -0,0 +1,786 @@
+//! An in-memory vector index over the embeddings of one model, with top-k similarity search.
+
+use crate::embedding::Embeddings;
+use crate::{Result, StoneMasonError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Arbitrary JSON fields stored alongside an embedding, which searches can filter on.
+pub type Metadata = serde_json::Map<String, Value>;
+
+/// How similar two embeddings are. Results are ordered most similar first: by descending score
+/// for `Cosine` and `Dot`, and by ascending distance for `Euclidean`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Cosine,
+    Dot,
+    Euclidean,
+}
+
+impl Metric {
+    /// The score of `b` against `a`, which are of the same length.
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => {
+                let norms = dot(a, a).sqrt() * dot(b, b).sqrt();
+                if norms == 0.0 {
+                    0.0
+                } else {
+                    dot(a, b) / norms
+                }
+            }
+            Metric::Dot => dot(a, b),
+            Metric::Euclidean => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt(),
+        }
+    }
+
+    /// Orders scores most similar first.
+    fn compare(&self, a: f32, b: f32) -> Ordering {
+        match self {
+            Metric::Cosine | Metric::Dot => b.total_cmp(&a),
+            Metric::Euclidean => a.total_cmp(&b),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Metric::Cosine => 0,
+            Metric::Dot => 1,
+            Metric::Euclidean => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Metric::Cosine),
+            1 => Ok(Metric::Dot),
+            2 => Ok(Metric::Euclidean),
+            other => Err(StoneMasonError::InvalidIndexFile(format!(
+                "unknown metric {other}"
+            ))),
+        }
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Restricts a search to records whose metadata matches.
+///
+/// ```
+/// use serde_json::json;
+/// use stone_mason::index::Filter;
+///
+/// let filter = Filter::and([
+///     Filter::eq("lang", "en"),
+///     Filter::not(Filter::one_of("category", [json!("draft"), json!("archived")])),
+/// ]);
+///
+/// let metadata = json!({"lang": "en", "category": "blog"});
+/// assert!(filter.matches(metadata.as_object().unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// The field is equal to the value.
+    Eq(String, Value),
+
+    /// The field is equal to any of the values.
+    OneOf(String, Vec<Value>),
+
+    /// The field is present, whatever its value.
+    Exists(String),
+
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn eq(key: impl Into<String>, value: impl Into<Value>) -> Self {
+        Filter::Eq(key.into(), value.into())
+    }
+
+    pub fn one_of(key: impl Into<String>, values: impl IntoIterator<Item = Value>) -> Self {
+        Filter::OneOf(key.into(), values.into_iter().collect())
+    }
+
+    pub fn exists(key: impl Into<String>) -> Self {
+        Filter::Exists(key.into())
+    }
+
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::And(filters.into_iter().collect())
+    }
+
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::Or(filters.into_iter().collect())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(filter: Filter) -> Self {
+        Filter::Not(Box::new(filter))
+    }
+
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        match self {
+            Filter::Eq(key, value) => metadata.get(key) == Some(value),
+            Filter::OneOf(key, values) => metadata.get(key).is_some_and(|v| values.contains(v)),
+            Filter::Exists(key) => metadata.contains_key(key),
+            Filter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            Filter::Not(filter) => !filter.matches(metadata),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    pub id: String,
+    pub embedding: Vec<f32>,
+
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult<'a> {
+    pub id: &'a str,
+
+    /// Similarity or distance to the query, depending on the index's [`Metric`].
+    pub score: f32,
+
+    pub metadata: &'a Metadata,
+}
+
+/// The file formats an index can be saved in. JSONL is readable and diffable, the binary
+/// format is around a quarter of the size for typical embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// A header line with the model id, dimension and metric, followed by one [`Record`] per
+    /// line.
+    Jsonl,
+
+    /// Little-endian, starting with [`BINARY_MAGIC`].
+    Binary,
+}
+
+/// The first bytes of an index saved in [`IndexFormat::Binary`].
+pub const BINARY_MAGIC: &[u8; 4] = b"SMVI";
+
+const BINARY_VERSION: u8 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    model_id: String,
+    dimension: usize,
+    metric: Metric,
+}
+
+/// Records of `(id, embedding, metadata)`, all embedded by the same model. Adding or querying
+/// with embeddings of another model or length fails, so incompatible embeddings are never
+/// compared.
+///
+/// ```
+/// use serde_json::json;
+/// use stone_mason::index::{Filter, Metadata, Metric, VectorIndex};
+///
+/// let mut index = VectorIndex::new("amazon.titan-embed-text-v2:0", 3, Metric::Cosine);
+/// let lang = |lang: &str| json!({ "lang": lang }).as_object().unwrap().clone();
+/// index.insert("a", vec![1.0, 0.0, 0.0], lang("en")).unwrap();
+/// index.insert("b", vec![0.0, 1.0, 0.0], lang("fr")).unwrap();
+/// index.insert("c", vec![0.9, 0.1, 0.0], lang("fr")).unwrap();
+///
+/// let results = index.search(&[1.0, 0.0, 0.0], 2, None).unwrap();
+/// assert_eq!(results[0].id, "a");
+/// assert_eq!(results[1].id, "c");
+///
+/// let results = index
+///     .search(&[1.0, 0.0, 0.0], 2, Some(&Filter::eq("lang", "fr")))
+///     .unwrap();
+/// assert_eq!(results[0].id, "c");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorIndex {
+    model_id: String,
+    dimension: usize,
+    metric: Metric,
+    records: Vec<Record>,
+    positions: HashMap<String, usize>,
+}
+
+impl VectorIndex {
+    pub fn new(model_id: impl Into<String>, dimension: usize, metric: Metric) -> Self {
+        VectorIndex {
+            model_id: model_id.into(),
+            dimension,
+            metric,
+            records: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// An empty index for embeddings like `embeddings`, from the same model and of the same
+    /// length.
+    pub fn for_embeddings(embeddings: &Embeddings, metric: Metric) -> Self {
+        VectorIndex::new(&embeddings.model_id, embeddings.dimension, metric)
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Record> {
+        self.positions.get(id).map(|&i| &self.records[i])
+    }
+
+    /// Adds a record, replacing any record with the same id.
+    pub fn insert(
+        &mut self,
+        id: impl Into<String>,
+        embedding: Vec<f32>,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.check_dimension(embedding.len())?;
+        let record = Record {
+            id: id.into(),
+            embedding,
+            metadata,
+        };
+        match self.positions.get(&record.id) {
+            Some(&i) => self.records[i] = record,
+            None => {
+                self.positions.insert(record.id.clone(), self.records.len());
+                self.records.push(record);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a record for each of `embeddings`, with the id and metadata at the same position in
+    /// `records`. Fails if the embeddings are from another model.
+    pub fn extend(
+        &mut self,
+        embeddings: Embeddings,
+        records: impl IntoIterator<Item = (String, Metadata)>,
+    ) -> Result<()> {
+        self.check_model(&embeddings.model_id)?;
+        let records: Vec<(String, Metadata)> = records.into_iter().collect();
+        if records.len() != embeddings.vectors.len() {
+            return Err(StoneMasonError::invalid_params(format!(
+                "got {} records for {} embeddings",
+                records.len(),
+                embeddings.vectors.len()
+            )));
+        }
+        for ((id, metadata), embedding) in records.into_iter().zip(embeddings.vectors) {
+            self.insert(id, embedding, metadata)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Record> {
+        let i = self.positions.remove(id)?;
+        let record = self.records.swap_remove(i);
+        if let Some(moved) = self.records.get(i) {
+            self.positions.insert(moved.id.clone(), i);
+        }
+        Some(record)
+    }
+
+    /// The `k` records most similar to `query` which match `filter`, most similar first.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<SearchResult<'_>>> {
+        self.check_dimension(query.len())?;
+        let mut results: Vec<SearchResult> = self
+            .records
+            .iter()
+            .filter(|record| match filter {
+                Some(filter) => filter.matches(&record.metadata),
+                None => true,
+            })
+            .map(|record| SearchResult {
+                id: &record.id,
+                score: self.metric.score(query, &record.embedding),
+                metadata: &record.metadata,
+            })
+            .collect();
+
+        let compare = |a: &SearchResult, b: &SearchResult| self.metric.compare(a.score, b.score);
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        if results.len() > k {
+            results.select_nth_unstable_by(k - 1, compare);
+            results.truncate(k);
+        }
+        results.sort_by(compare);
+        Ok(results)
+    }
+
+    /// Searches with each of `queries`, which must be from the index's model, e.g. queries
+    /// embedded with [`crate::StoneMason::embed`].
+    pub fn query(
+        &self,
+        queries: &Embeddings,
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<Vec<SearchResult<'_>>>> {
+        self.check_model(&queries.model_id)?;
+        queries
+            .vectors
+            .iter()
+            .map(|query| self.search(query, k, filter))
+            .collect()
+    }
+
+    fn check_model(&self, model_id: &str) -> Result<()> {
+        if model_id != self.model_id {
+            return Err(StoneMasonError::IncompatibleEmbeddings {
+                expected: format!("embeddings from {}", self.model_id),
+                found: format!("embeddings from {model_id}"),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_dimension(&self, dimension: usize) -> Result<()> {
+        if dimension != self.dimension {
+            return Err(StoneMasonError::IncompatibleEmbeddings {
+                expected: format!("an embedding of length {}", self.dimension),
+                found: format!("one of length {dimension}"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes the index to `path` in `format`. The binary format stores the dimension as a
+    /// `u32`, so cannot hold embeddings longer than `u32::MAX`.
+    pub fn save(&self, path: impl AsRef<Path>, format: IndexFormat) -> Result<()> {
+        if format == IndexFormat::Binary && u32::try_from(self.dimension).is_err() {
+            return Err(StoneMasonError::invalid_params(format!(
+                "dimension {} is too large for the binary index format",
+                self.dimension
+            )));
+        }
+        let path = path.as_ref();
+        let io_error = |source| StoneMasonError::Io {
+            source,
+            path: path.to_path_buf(),
+        };
+        let file = std::fs::File::create(path).map_err(io_error)?;
+        let mut writer = BufWriter::new(file);
+        match format {
+            IndexFormat::Jsonl => self.write_jsonl(&mut writer),
+            IndexFormat::Binary => self.write_binary(&mut writer),
+        }
+        .map_err(io_error)?;
+        writer.flush().map_err(io_error)
+    }
+
+    /// Reads an index saved with [`VectorIndex::save`], in either format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let io_error = |source| StoneMasonError::Io {
+            source,
+            path: path.to_path_buf(),
+        };
+        let file = std::fs::File::open(path).map_err(io_error)?;
+        let mut reader = BufReader::new(file);
+        let is_binary = reader
+            .fill_buf()
+            .map_err(io_error)?
+            .starts_with(BINARY_MAGIC);
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(io_error)?;
+        if is_binary {
+            VectorIndex::from_binary(&bytes)
+        } else {
+            VectorIndex::from_jsonl(&bytes)
+        }
+    }
+
+    fn header(&self) -> Header {
+        Header {
+            model_id: self.model_id.clone(),
+            dimension: self.dimension,
+            metric: self.metric,
+        }
+    }
+
+    fn write_jsonl(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        serde_json::to_writer(&mut *writer, &self.header())?;
+        writeln!(writer)?;
+        for record in &self.records {
+            serde_json::to_writer(&mut *writer, record)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn from_jsonl(bytes: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|e| StoneMasonError::InvalidIndexFile(e.to_string()))?;
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty());
+        let parse_error = |line: usize, e: serde_json::Error| {
+            StoneMasonError::InvalidIndexFile(format!("line {}: {e}", line + 1))
+        };
+
+        let Some((n, header)) = lines.next() else {
+            return Err(StoneMasonError::InvalidIndexFile(
+                "missing header".to_string(),
+            ));
+        };
+        let header: Header = serde_json::from_str(header).map_err(|e| parse_error(n, e))?;
+        let mut index = VectorIndex::new(header.model_id, header.dimension, header.metric);
+        for (n, line) in lines {
+            let record: Record = serde_json::from_str(line).map_err(|e| parse_error(n, e))?;
+            index.insert(record.id, record.embedding, record.metadata)?;
+        }
+        Ok(index)
+    }
+
+    /// Writes the header, then each record as its id, embedding and metadata as JSON. Strings
+    /// are prefixed with their length in bytes.
+    fn write_binary(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&[BINARY_VERSION, self.metric.to_byte()])?;
+        write_bytes(writer, self.model_id.as_bytes())?;
+        writer.write_all(&to_u32(self.dimension)?.to_le_bytes())?;
+        writer.write_all(&(self.records.len() as u64).to_le_bytes())?;
+        for record in &self.records {
+            write_bytes(writer, record.id.as_bytes())?;
+            for value in &record.embedding {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            write_bytes(writer, &serde_json::to_vec(&record.metadata)?)?;
+        }
+        Ok(())
+    }
+
+    fn from_binary(bytes: &[u8]) -> Result<Self> {
+        let mut reader = BinaryReader { bytes };
+        if reader.take(BINARY_MAGIC.len())? != BINARY_MAGIC {
+            return Err(StoneMasonError::InvalidIndexFile(
+                "missing magic bytes".to_string(),
+            ));
+        }
+        let [version, metric] = reader.array()?;
+        if version != BINARY_VERSION {
+            return Err(StoneMasonError::InvalidIndexFile(format!(
+                "unsupported version {version}"
+            )));
+        }
+        let metric = Metric::from_byte(metric)?;
+        let model_id = reader.string()?;
+        let dimension = u32::from_le_bytes(reader.array()?) as usize;
+        let count = u64::from_le_bytes(reader.array()?);
+
+        let mut index = VectorIndex::new(model_id, dimension, metric);
+        for _ in 0..count {
+            let id = reader.string()?;
+            let embedding = (0..dimension)
+                .map(|_| reader.array().map(f32::from_le_bytes))
+                .collect::<Result<Vec<f32>>>()?;
+            let metadata = serde_json::from_slice(reader.bytes()?)
+                .map_err(|e| StoneMasonError::InvalidIndexFile(e.to_string()))?;
+            index.insert(id, embedding, metadata)?;
+        }
+        if !reader.bytes.is_empty() {
+            return Err(StoneMasonError::InvalidIndexFile(format!(
+                "{} unexpected bytes after the last record",
+                reader.bytes.len()
+            )));
+        }
+        Ok(index)
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&to_u32(bytes.len())?.to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Lengths are stored as `u32`s, which larger lengths cannot be written as.
+fn to_u32(len: usize) -> std::io::Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("length {len} is too large for the binary index format"),
+        )
+    })
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(StoneMasonError::InvalidIndexFile("truncated".to_string()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// Bytes prefixed with their length.
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| StoneMasonError::InvalidIndexFile(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(value: Value) -> Metadata {
+        value.as_object().unwrap().clone()
+    }
+
+    fn sample_index(metric: Metric) -> VectorIndex {
+        let mut index = VectorIndex::new("cohere.embed-english-v3", 2, metric);
+        index
+            .insert("north", vec![0.0, 1.0], metadata(json!({"lang": "en"})))
+            .unwrap();
+        index
+            .insert("east", vec![1.0, 0.0], metadata(json!({"lang": "fr"})))
+            .unwrap();
+        index
+            .insert(
+                "far-east",
+                vec![3.0, 0.1],
+                metadata(json!({"lang": "en", "draft": true})),
+            )
+            .unwrap();
+        index
+    }
+
+    fn ids<'a>(results: &[SearchResult<'a>]) -> Vec<&'a str> {
+        results.iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn test_metrics() {
+        let query = [1.0, 0.0];
+
+        let index = sample_index(Metric::Cosine);
+        let results = index.search(&query, 3, None).unwrap();
+        assert_eq!(ids(&results), vec!["east", "far-east", "north"]);
+        assert_eq!(results[0].score, 1.0);
+
+        let index = sample_index(Metric::Dot);
+        let results = index.search(&query, 2, None).unwrap();
+        assert_eq!(ids(&results), vec!["far-east", "east"]);
+        assert_eq!(results[0].score, 3.0);
+
+        let index = sample_index(Metric::Euclidean);
+        let results = index.search(&query, 1, None).unwrap();
+        assert_eq!(ids(&results), vec!["east"]);
+        assert_eq!(results[0].score, 0.0);
+
+        assert!(index.search(&query, 0, None).unwrap().is_empty());
+        assert_eq!(Metric::Cosine.score(&[0.0, 0.0], &query), 0.0);
+    }
+
+    #[test]
+    fn test_filters() {
+        let index = sample_index(Metric::Cosine);
+        let query = [1.0, 0.0];
+
+        let filter = Filter::eq("lang", "en");
+        let results = index.search(&query, 3, Some(&filter)).unwrap();
+        assert_eq!(ids(&results), vec!["far-east", "north"]);
+
+        let filter = Filter::and([
+            Filter::eq("lang", "en"),
+            Filter::not(Filter::exists("draft")),
+        ]);
+        let results = index.search(&query, 3, Some(&filter)).unwrap();
+        assert_eq!(ids(&results), vec!["north"]);
+
+        let filter = Filter::or([
+            Filter::one_of("lang", [json!("fr"), json!("de")]),
+            Filter::eq("draft", true),
+        ]);
+        let results = index.search(&query, 3, Some(&filter)).unwrap();
+        assert_eq!(ids(&results), vec!["east", "far-east"]);
+    }
+
+    #[test]
+    fn test_insert_replace_and_remove() {
+        let mut index = sample_index(Metric::Cosine);
+        index
+            .insert("north", vec![0.0, -1.0], Metadata::new())
+            .unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get("north").unwrap().embedding, vec![0.0, -1.0]);
+
+        assert_eq!(index.remove("north").unwrap().id, "north");
+        assert!(index.remove("north").is_none());
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get("far-east").unwrap().embedding, vec![3.0, 0.1]);
+    }
+
+    #[test]
+    fn test_rejects_incompatible_embeddings() {
+        let mut index = sample_index(Metric::Cosine);
+
+        let err = index
+            .insert("up", vec![0.0, 0.0, 1.0], Metadata::new())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "incompatible embeddings: expected an embedding of length 2, got one of length 3"
+        );
+        assert!(index.search(&[1.0], 1, None).is_err());
+
+        let titan = Embeddings {
+            model_id: "amazon.titan-embed-text-v2:0".to_string(),
+            dimension: 2,
+            vectors: vec![vec![1.0, 0.0]],
+        };
+        let err = index.query(&titan, 1, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "incompatible embeddings: expected embeddings from cohere.embed-english-v3, got embeddings from amazon.titan-embed-text-v2:0"
+        );
+        assert!(index
+            .extend(titan, [("up".to_string(), Metadata::new())])
+            .is_err());
+
+        let cohere = Embeddings {
+            model_id: "cohere.embed-english-v3".to_string(),
+            dimension: 2,
+            vectors: vec![vec![0.0, 1.0]],
+        };
+        let results = index.query(&cohere, 1, None).unwrap();
+        assert_eq!(ids(&results[0]), vec!["north"]);
+        index
+            .extend(cohere, [("south".to_string(), Metadata::new())])
+            .unwrap();
+        assert_eq!(index.len(), 4);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let index = sample_index(Metric::Dot);
+        let dir = std::env::temp_dir().join(format!("stone-mason-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (format, file_name) in [
+            (IndexFormat::Jsonl, "index.jsonl"),
+            (IndexFormat::Binary, "index.bin"),
+        ] {
+            let path = dir.join(file_name);
+            index.save(&path, format).unwrap();
+            assert_eq!(VectorIndex::load(&path).unwrap(), index);
+        }
+
+        let jsonl = std::fs::read_to_string(dir.join("index.jsonl")).unwrap();
+        assert_eq!(
+            jsonl.lines().next().unwrap(),
+            r#"{"model_id":"cohere.embed-english-v3","dimension":2,"metric":"dot"}"#
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_binary_dimension_limit() {
+        let index = VectorIndex::new("m", u32::MAX as usize + 1, Metric::Dot);
+        let path =
+            std::env::temp_dir().join(format!("stone-mason-oversized-{}.bin", std::process::id()));
+
+        let err = index.save(&path, IndexFormat::Binary).unwrap_err();
+        assert!(matches!(err, StoneMasonError::InvalidParams { .. }));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let mut bytes = Vec::new();
+        sample_index(Metric::Cosine)
+            .write_binary(&mut bytes)
+            .unwrap();
+
+        let err = VectorIndex::from_binary(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid index file: truncated");
+
+        bytes.push(0);
+        let err = VectorIndex::from_binary(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid index file: 1 unexpected bytes after the last record"
+        );
+
+        let err = VectorIndex::from_jsonl(b"").unwrap_err();
+        assert_eq!(err.to_string(), "invalid index file: missing header");
+
+        let jsonl = b"{\"model_id\":\"m\",\"dimension\":2,\"metric\":\"dot\"}\n{\"id\":\"a\"}\n";
+        let err = VectorIndex::from_jsonl(jsonl).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid index file: line 2: missing field `embedding`"));
+
+        let jsonl = b"{\"model_id\":\"m\",\"dimension\":2,\"metric\":\"dot\"}\n{\"id\":\"a\",\"embedding\":[1.0]}\n";
+        let err = VectorIndex::from_jsonl(jsonl).unwrap_err();
+        assert!(matches!(
+            err,
+            StoneMasonError::IncompatibleEmbeddings { .. }
+        ));
+    }
+}
@@ -1,69 +1,272 @@
+#[macro_use]
+mod macros;
+
 pub mod ai21;
 pub mod amazon;
 pub mod anthropic;
+pub mod catalog;
 pub mod cohere;
+pub mod completion;
+pub mod embedding;
+pub mod error;
+pub mod identifier;
+pub mod image;
+pub mod index;
 pub mod meta;
+pub mod mistral;
 pub mod stability;
+pub mod stream;
 
 use crate::ai21::AI21LabsModel;
-use crate::amazon::AmazonModel;
-use crate::anthropic::AnthropicModel;
-use anyhow::Result;
+use crate::amazon::{
+    AmazonModel, TitanEmbeddingModel, TitanEmbeddingV2Model, TitanImageModel,
+    TitanMultimodalEmbeddingModel, TitanTextModel,
+};
+use crate::anthropic::{AnthropicModel, ClaudeMessagesModel, ClaudeTextModel};
 use aws_sdk_bedrockruntime::operation::invoke_model::InvokeModelOutput;
-use cohere::CohereModel;
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::types::ResponseStream;
+use aws_sdk_bedrockruntime::Client;
+use cohere::{CohereChatModel, CohereCommandModel, CohereEmbedModel, CohereModel};
+pub use error::{Result, StoneMasonError};
+use futures::Stream;
 use meta::MetaModel;
-use serde::Deserialize;
+use mistral::MistralModel;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use stability::StabilityAIModel;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use stream::{StreamEvent, StreamingModel};
 
-/// | Provider     | Model name                 | Version | Model Id                         |
-/// |--------------|----------------------------|---------|----------------------------------|
-/// | AI21 Labs    | Jurassic-2 Mid             | 1.x     | ai21.j2-mid-v1                   |
-/// | AI21 Labs    | Jurassic-2 Ultra           | 1.x     | ai21.j2-ultra-v1                 |
-/// | Amazon       | Titan Text G1 - Lite       | 1.x     | amazon.titan-text-lite-v1        |
-/// | Amazon       | Titan Embeddings G1 - Text | 1.x     | amazon.titan-embed-text-v1       |
-/// | Amazon       | Titan Text G1 - Express    | 1.x     | amazon.titan-text-express-v1     |
-/// | Amazon       | Titan Text G1 - Agile      | 1.x     | amazon.titan-text-agile-v1       |
-/// | Anthropic    | Claude                     | 1.x     | anthropic.claude-v1              |
-/// | Anthropic    | Claude                     | 2.x     | anthropic.claude-v2              |
-/// | Anthropic    | Claude Instant             | 1.x     | anthropic.claude-instant-v1      |
-/// | Cohere       | Command                    | 14.x    | cohere.command-text-v14          |
-/// | Cohere       | Command Light              | 15.x    | cohere.command-light-text-v14    |
-/// | Cohere       | Embed English              | 3.x     | cohere.embed-english-v3          |
-/// | Cohere       | Embed Multilingual         | 3.x     | cohere.embed-multilingual-v3     |
-/// | Meta         | Llama 2 Chat 13B           | 1.x     | meta.llama2-13b-chat-v1          |
-/// | Stability AI | Stable Diffusion XL        | 0.x     | stability.stable-diffusion-xl-v0 |
-
+/// Every model available on Bedrock. Each provider's model enum has one variant per model and
+/// version, so only model ids which exist can be constructed.
+///
+/// | Provider     | Model name                     | Version | Variant                                    | Model Id                                |
+/// |--------------|--------------------------------|---------|--------------------------------------------|-----------------------------------------|
+/// | AI21 Labs    | Jurassic-2 Mid                 | 1.x     | `AI21LabsModel::Jurassic2MidV1`            | ai21.j2-mid-v1                          |
+/// | AI21 Labs    | Jurassic-2 Ultra               | 1.x     | `AI21LabsModel::Jurassic2UltraV1`          | ai21.j2-ultra-v1                        |
+/// | Amazon       | Titan Text G1 - Lite           | 1.x     | `AmazonModel::TitanTextLiteV1`             | amazon.titan-text-lite-v1               |
+/// | Amazon       | Titan Embeddings G1 - Text     | 1.x     | `AmazonModel::TitanEmbeddingsTextV1`       | amazon.titan-embed-text-v1              |
+/// | Amazon       | Titan Text G1 - Express        | 1.x     | `AmazonModel::TitanTextExpressV1`          | amazon.titan-text-express-v1            |
+/// | Amazon       | Titan Text G1 - Agile          | 1.x     | `AmazonModel::TitanTextAgileV1`            | amazon.titan-text-agile-v1              |
+/// | Amazon       | Titan Image Generator G1       | 1.x     | `AmazonModel::TitanImageGeneratorV1`       | amazon.titan-image-generator-v1         |
+/// | Amazon       | Titan Text Embeddings V2       | 2.0     | `AmazonModel::TitanEmbeddingsTextV2`       | amazon.titan-embed-text-v2:0            |
+/// | Amazon       | Titan Multimodal Embeddings G1 | 1.x     | `AmazonModel::TitanMultimodalEmbeddingsV1` | amazon.titan-embed-image-v1             |
+/// | Anthropic    | Claude                         | 1.x     | `AnthropicModel::ClaudeV1`                 | anthropic.claude-v1                     |
+/// | Anthropic    | Claude                         | 2.x     | `AnthropicModel::ClaudeV2`                 | anthropic.claude-v2                     |
+/// | Anthropic    | Claude                         | 2.1     | `AnthropicModel::ClaudeV2_1`               | anthropic.claude-v2:1                   |
+/// | Anthropic    | Claude Instant                 | 1.x     | `AnthropicModel::ClaudeInstantV1`          | anthropic.claude-instant-v1             |
+/// | Anthropic    | Claude 3 Haiku                 | 1.0     | `AnthropicModel::Claude3HaikuV1`           | anthropic.claude-3-haiku-20240307-v1:0  |
+/// | Anthropic    | Claude 3 Sonnet                | 1.0     | `AnthropicModel::Claude3SonnetV1`          | anthropic.claude-3-sonnet-20240229-v1:0 |
+/// | Anthropic    | Claude 3 Opus                  | 1.0     | `AnthropicModel::Claude3OpusV1`            | anthropic.claude-3-opus-20240229-v1:0   |
+/// | Cohere       | Command                        | 14.x    | `CohereModel::CommandV14`                  | cohere.command-text-v14                 |
+/// | Cohere       | Command Light                  | 15.x    | `CohereModel::CommandLightV14`             | cohere.command-light-text-v14           |
+/// | Cohere       | Command R                      | 1.0     | `CohereModel::CommandRV1`                  | cohere.command-r-v1:0                   |
+/// | Cohere       | Command R+                     | 1.0     | `CohereModel::CommandRPlusV1`              | cohere.command-r-plus-v1:0              |
+/// | Cohere       | Embed English                  | 3.x     | `CohereModel::EmbedEnglishV3`              | cohere.embed-english-v3                 |
+/// | Cohere       | Embed Multilingual             | 3.x     | `CohereModel::EmbedMultilingualV3`         | cohere.embed-multilingual-v3            |
+/// | Meta         | Llama 2 Chat 13B               | 1.x     | `MetaModel::Llama2Chat13BV1`               | meta.llama2-13b-chat-v1                 |
+/// | Meta         | Llama 2 Chat 70B               | 1.x     | `MetaModel::Llama2Chat70BV1`               | meta.llama2-70b-chat-v1                 |
+/// | Meta         | Llama 3 8B Instruct            | 1.0     | `MetaModel::Llama3Instruct8BV1`            | meta.llama3-8b-instruct-v1:0            |
+/// | Meta         | Llama 3 70B Instruct           | 1.0     | `MetaModel::Llama3Instruct70BV1`           | meta.llama3-70b-instruct-v1:0           |
+/// | Mistral AI   | Mistral 7B Instruct            | 0.2     | `MistralModel::Mistral7BInstructV0_2`      | mistral.mistral-7b-instruct-v0:2        |
+/// | Mistral AI   | Mixtral 8x7B Instruct          | 0.1     | `MistralModel::Mixtral8x7BInstructV0_1`    | mistral.mixtral-8x7b-instruct-v0:1      |
+/// | Mistral AI   | Mistral Large                  | 1.0     | `MistralModel::MistralLargeV1`             | mistral.mistral-large-2402-v1:0         |
+/// | Stability AI | Stable Diffusion XL            | 0.x     | `StabilityAIModel::StableDiffusionXLV0`    | stability.stable-diffusion-xl-v0        |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BaseModel {
     AI21Labs(AI21LabsModel),
     Amazon(AmazonModel),
     Anthropic(AnthropicModel),
     Cohere(CohereModel),
     Meta(MetaModel),
+    Mistral(MistralModel),
     StabilityAI(StabilityAIModel),
 }
 
-#[derive(PartialEq)]
-pub enum ModelVersion {
-    V0,
-    V1,
-    V2,
-    V3,
-    V14,
-    V15,
+impl BaseModel {
+    /// Full Bedrock model id, e.g. `anthropic.claude-v2`.
+    pub fn model_id(&self) -> String {
+        match self {
+            BaseModel::AI21Labs(model) => model.model_id(),
+            BaseModel::Amazon(model) => model.model_id(),
+            BaseModel::Anthropic(model) => model.model_id(),
+            BaseModel::Cohere(model) => model.model_id(),
+            BaseModel::Meta(model) => model.model_id(),
+            BaseModel::Mistral(model) => model.model_id(),
+            BaseModel::StabilityAI(model) => model.model_id(),
+        }
+    }
+
+    /// Every known model, in the order of the [`BaseModel`] table.
+    pub fn all() -> impl Iterator<Item = BaseModel> {
+        let ai21 = AI21LabsModel::ALL.into_iter().map(BaseModel::from);
+        let amazon = AmazonModel::ALL.into_iter().map(BaseModel::from);
+        let anthropic = AnthropicModel::ALL.into_iter().map(BaseModel::from);
+        let cohere = CohereModel::ALL.into_iter().map(BaseModel::from);
+        let meta = MetaModel::ALL.into_iter().map(BaseModel::from);
+        let mistral = MistralModel::ALL.into_iter().map(BaseModel::from);
+        let stability = StabilityAIModel::ALL.into_iter().map(BaseModel::from);
+        ai21.chain(amazon)
+            .chain(anthropic)
+            .chain(cohere)
+            .chain(meta)
+            .chain(mistral)
+            .chain(stability)
+    }
 }
 
 impl Display for BaseModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let id: String = match self {
-            BaseModel::AI21Labs(model) => format!("ai21.{model}"),
-            BaseModel::Amazon(model) => format!("amazon.{model}"),
-            BaseModel::Anthropic(model) => format!("anthropic.{model}"),
-            BaseModel::Cohere(model) => format!("cohere.{model}"),
-            BaseModel::Meta(model) => format!("meta.{model}"),
-            BaseModel::StabilityAI(model) => format!("stability.{model}"),
+        write!(f, "{}", self.model_id())
+    }
+}
+
+/// Parses a full Bedrock model id, e.g. `anthropic.claude-v2`.
+impl FromStr for BaseModel {
+    type Err = StoneMasonError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((provider, _)) = s.split_once('.') else {
+            return Err(StoneMasonError::UnknownModelId(s.to_string()));
         };
-        write!(f, "{id}")
+        match provider {
+            AI21LabsModel::PROVIDER => s.parse::<AI21LabsModel>().map(BaseModel::from),
+            AmazonModel::PROVIDER => s.parse::<AmazonModel>().map(BaseModel::from),
+            AnthropicModel::PROVIDER => s.parse::<AnthropicModel>().map(BaseModel::from),
+            CohereModel::PROVIDER => s.parse::<CohereModel>().map(BaseModel::from),
+            MetaModel::PROVIDER => s.parse::<MetaModel>().map(BaseModel::from),
+            MistralModel::PROVIDER => s.parse::<MistralModel>().map(BaseModel::from),
+            StabilityAIModel::PROVIDER => s.parse::<StabilityAIModel>().map(BaseModel::from),
+            _ => Err(StoneMasonError::UnknownModelId(s.to_string())),
+        }
+    }
+}
+
+macro_rules! impl_provider_model_from_str {
+    ($($model:ty),* $(,)?) => {
+        $(
+            /// Parses a model id, with or without the provider prefix.
+            impl FromStr for $model {
+                type Err = StoneMasonError;
+
+                fn from_str(s: &str) -> Result<Self> {
+                    let id = s
+                        .strip_prefix(<$model>::PROVIDER)
+                        .and_then(|id| id.strip_prefix('.'))
+                        .unwrap_or(s);
+                    <$model>::ALL
+                        .into_iter()
+                        .find(|model| model.to_string() == id)
+                        .ok_or_else(|| StoneMasonError::UnknownModelId(s.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+impl_provider_model_from_str! {
+    AI21LabsModel,
+    AmazonModel,
+    AnthropicModel,
+    CohereModel,
+    MetaModel,
+    MistralModel,
+    StabilityAIModel,
+}
+
+/// Models are serialized as their full model id, so they can be stored as plain strings.
+macro_rules! impl_model_id_serde {
+    ($($model:ty),* $(,)?) => {
+        $(
+            impl Serialize for $model {
+                fn serialize<S: Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> std::result::Result<S::Ok, S::Error> {
+                    serializer.serialize_str(&self.model_id())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $model {
+                fn deserialize<D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> std::result::Result<Self, D::Error> {
+                    let id = String::deserialize(deserializer)?;
+                    id.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+impl_model_id_serde! {
+    BaseModel,
+    AI21LabsModel,
+    AmazonModel,
+    TitanTextModel,
+    TitanEmbeddingModel,
+    TitanEmbeddingV2Model,
+    TitanMultimodalEmbeddingModel,
+    TitanImageModel,
+    AnthropicModel,
+    ClaudeTextModel,
+    ClaudeMessagesModel,
+    CohereModel,
+    CohereCommandModel,
+    CohereChatModel,
+    CohereEmbedModel,
+    MetaModel,
+    MistralModel,
+    StabilityAIModel,
+}
+
+macro_rules! impl_from_provider_model {
+    ($($model:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$model> for BaseModel {
+                fn from(model: $model) -> Self {
+                    BaseModel::$variant(model)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_provider_model! {
+    AI21LabsModel => AI21Labs,
+    AmazonModel => Amazon,
+    AnthropicModel => Anthropic,
+    CohereModel => Cohere,
+    MetaModel => Meta,
+    MistralModel => Mistral,
+    StabilityAIModel => StabilityAI,
+}
+
+/// Links a model enum to the request params and response types its models accept, so that
+/// mismatched request/response pairs are rejected at compile time. Providers whose models
+/// span several APIs have one such enum per API, e.g. [`amazon::TitanTextModel`] and
+/// [`amazon::TitanImageModel`].
+pub trait BedrockModel: Display {
+    /// Provider prefix of the model id, e.g. `anthropic` in `anthropic.claude-v2`.
+    const PROVIDER: &'static str;
+
+    /// Request body sent to the model.
+    type Params: Serialize;
+
+    /// Response body returned by the model.
+    type Response: DeserializeOwned;
+
+    /// Full Bedrock model id, e.g. `anthropic.claude-v2`.
+    fn model_id(&self) -> String {
+        format!("{}.{self}", Self::PROVIDER)
+    }
+
+    /// Checks `params` against the limits of this particular model. Called before every
+    /// invocation, so out of range values are reported before a request is sent.
+    fn validate_params(&self, _params: &Self::Params) -> Result<()> {
+        Ok(())
     }
 }
 
@@ -72,8 +275,124 @@ where
     T: Deserialize<'de>,
 {
     fn from_model_output(output: &'de InvokeModelOutput) -> Result<T> {
-        let res = std::str::from_utf8(output.body.as_ref())?;
-        Ok(serde_json::from_str(res)?)
+        let body = output.body.as_ref();
+        let res = std::str::from_utf8(body).map_err(|source| StoneMasonError::InvalidUtf8 {
+            source,
+            body: body.to_vec(),
+        })?;
+        serde_json::from_str(res).map_err(|e| StoneMasonError::deserialization(e, body))
+    }
+}
+
+/// Content type used for both the request body and the `Accept` header of every invocation.
+const CONTENT_TYPE: &str = "application/json";
+
+/// High-level client for invoking Bedrock models.
+///
+/// Wraps an [`aws_sdk_bedrockruntime::Client`] and takes care of serializing the request
+/// params, setting the content type headers and deserializing the model's response.
+#[derive(Clone, Debug)]
+pub struct StoneMason {
+    client: Client,
+}
+
+impl StoneMason {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Returns the underlying Bedrock runtime client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Invokes `model` with `params` as the request body, and deserializes the response body
+    /// into the model's response type.
+    pub async fn invoke<M: BedrockModel>(
+        &self,
+        model: &M,
+        params: &M::Params,
+    ) -> Result<M::Response> {
+        model.validate_params(params)?;
+        self.invoke_model_id(&model.model_id(), params).await
+    }
+
+    /// Invokes `model` with `params` as the request body, yielding the response in chunks as it
+    /// is generated. The stream ends with a [`StreamEvent::End`] carrying the stop reason and
+    /// invocation metrics.
+    pub async fn invoke_stream<M: StreamingModel>(
+        &self,
+        model: &M,
+        params: &M::Params,
+    ) -> Result<impl Stream<Item = Result<StreamEvent<M::Chunk>>> + Send> {
+        model.validate_params(params)?;
+        let model_id = model.model_id();
+        let output = self
+            .client
+            .invoke_model_with_response_stream()
+            .model_id(&model_id)
+            .content_type(CONTENT_TYPE)
+            .accept(CONTENT_TYPE)
+            .body(stream_request_body(params, M::STREAM_FIELD)?)
+            .send()
+            .await?;
+
+        let parts = futures::stream::unfold(output.body, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(Some(ResponseStream::Chunk(part))) => {
+                        let bytes = part.bytes.map(Blob::into_inner).unwrap_or_default();
+                        return Some((Ok(bytes), receiver));
+                    }
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return None,
+                    Err(e) => return Some((Err(e.into()), receiver)),
+                }
+            }
+        });
+
+        Ok(stream::decode_stream(Box::pin(parts)))
+    }
+
+    async fn invoke_model_id<P, R>(&self, model_id: &str, params: &P) -> Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let output = self
+            .client
+            .invoke_model()
+            .model_id(model_id)
+            .content_type(CONTENT_TYPE)
+            .accept(CONTENT_TYPE)
+            .body(request_body(params)?)
+            .send()
+            .await?;
+
+        let body = output.body.as_ref();
+        serde_json::from_slice(body).map_err(|e| StoneMasonError::deserialization(e, body))
+    }
+}
+
+fn request_body<P: Serialize>(params: &P) -> Result<Blob> {
+    let body = serde_json::to_vec(params).map_err(StoneMasonError::Serialization)?;
+    Ok(Blob::new(body))
+}
+
+fn stream_request_body<P: Serialize>(params: &P, stream_field: bool) -> Result<Blob> {
+    if !stream_field {
+        return request_body(params);
+    }
+    let mut body = serde_json::to_value(params).map_err(StoneMasonError::Serialization)?;
+    if let Some(fields) = body.as_object_mut() {
+        fields.insert("stream".to_string(), serde_json::Value::Bool(true));
+    }
+    request_body(&body)
+}
+
+impl From<Client> for StoneMason {
+    fn from(client: Client) -> Self {
+        Self::new(client)
     }
 }
 
@@ -83,54 +402,191 @@ mod tests {
     use crate::ai21::AI21LabsModel;
     use crate::amazon::AmazonModel;
     use crate::anthropic::AnthropicModel;
-    use crate::ModelVersion::{V0, V1, V14, V15, V2, V3};
+    use crate::meta::MetaResponse;
+
+    /// Builds an [`InvokeModelOutput`] with `body` as its response body, for testing response
+    /// deserialization against fixtures.
+    pub(crate) fn model_output(body: &str) -> InvokeModelOutput {
+        InvokeModelOutput::builder()
+            .body(Blob::new(body))
+            .content_type(CONTENT_TYPE)
+            .build()
+            .unwrap()
+    }
 
     // Unit tests for ever single model version, in order
     #[test]
     fn test_base_model_to_string() {
-        let model = BaseModel::AI21Labs(AI21LabsModel::Jurassic2Mid(V1));
+        let model = BaseModel::AI21Labs(AI21LabsModel::Jurassic2MidV1);
         assert_eq!(model.to_string(), "ai21.j2-mid-v1");
 
-        let model = BaseModel::AI21Labs(AI21LabsModel::Jurassic2Ultra(V1));
+        let model = BaseModel::AI21Labs(AI21LabsModel::Jurassic2UltraV1);
         assert_eq!(model.to_string(), "ai21.j2-ultra-v1");
 
-        let model = BaseModel::Amazon(AmazonModel::TitanTextLite(V1));
+        let model = BaseModel::Amazon(AmazonModel::TitanTextLiteV1);
         assert_eq!(model.to_string(), "amazon.titan-text-lite-v1");
 
-        let model = BaseModel::Amazon(AmazonModel::TitanEmbeddingsText(V1));
+        let model = BaseModel::Amazon(AmazonModel::TitanEmbeddingsTextV1);
         assert_eq!(model.to_string(), "amazon.titan-embed-text-v1");
 
-        let model = BaseModel::Amazon(AmazonModel::TitanTextExpress(V1));
+        let model = BaseModel::Amazon(AmazonModel::TitanTextExpressV1);
         assert_eq!(model.to_string(), "amazon.titan-text-express-v1");
 
-        let model = BaseModel::Amazon(AmazonModel::TitanTextAgile(V1));
+        let model = BaseModel::Amazon(AmazonModel::TitanTextAgileV1);
         assert_eq!(model.to_string(), "amazon.titan-text-agile-v1");
 
-        let model = BaseModel::Anthropic(AnthropicModel::Claude(V1));
+        let model = BaseModel::Anthropic(AnthropicModel::ClaudeV1);
         assert_eq!(model.to_string(), "anthropic.claude-v1");
 
-        let model = BaseModel::Anthropic(AnthropicModel::Claude(V2));
+        let model = BaseModel::Anthropic(AnthropicModel::ClaudeV2);
         assert_eq!(model.to_string(), "anthropic.claude-v2");
 
-        let model = BaseModel::Anthropic(AnthropicModel::ClaudeInstant(V1));
+        let model = BaseModel::Anthropic(AnthropicModel::ClaudeInstantV1);
         assert_eq!(model.to_string(), "anthropic.claude-instant-v1");
 
-        let model = BaseModel::Cohere(CohereModel::Command(V14));
+        let model = BaseModel::Cohere(CohereModel::CommandV14);
         assert_eq!(model.to_string(), "cohere.command-text-v14");
 
-        let model = BaseModel::Cohere(CohereModel::CommandLight(V15));
+        let model = BaseModel::Cohere(CohereModel::CommandLightV14);
         assert_eq!(model.to_string(), "cohere.command-light-text-v14");
 
-        let model = BaseModel::Cohere(CohereModel::EmbedEnglish(V3));
+        let model = BaseModel::Cohere(CohereModel::EmbedEnglishV3);
         assert_eq!(model.to_string(), "cohere.embed-english-v3");
 
-        let model = BaseModel::Cohere(CohereModel::EmbedMultilingual(V3));
+        let model = BaseModel::Cohere(CohereModel::EmbedMultilingualV3);
         assert_eq!(model.to_string(), "cohere.embed-multilingual-v3");
 
-        let model = BaseModel::Meta(MetaModel::Llama2Chat13B(V1));
+        let model = BaseModel::Meta(MetaModel::Llama2Chat13BV1);
         assert_eq!(model.to_string(), "meta.llama2-13b-chat-v1");
 
-        let model = BaseModel::StabilityAI(StabilityAIModel::StableDiffusionXL(V0));
+        let model = BaseModel::StabilityAI(StabilityAIModel::StableDiffusionXLV0);
         assert_eq!(model.to_string(), "stability.stable-diffusion-xl-v0");
     }
+
+    #[test]
+    fn test_model_id_matches_base_model() {
+        let model = AnthropicModel::ClaudeInstantV1;
+        assert_eq!(model.model_id(), "anthropic.claude-instant-v1");
+        assert_eq!(
+            BaseModel::from(model).to_string(),
+            "anthropic.claude-instant-v1"
+        );
+
+        let model = CohereModel::EmbedEnglishV3;
+        assert_eq!(model.model_id(), "cohere.embed-english-v3");
+        assert_eq!(
+            BaseModel::from(model).to_string(),
+            "cohere.embed-english-v3"
+        );
+    }
+
+    #[test]
+    fn test_from_model_output_keeps_raw_body() {
+        let err = MetaResponse::from_model_output(&model_output("{\"generation\":")).unwrap_err();
+        assert!(
+            matches!(err, StoneMasonError::Deserialization { ref body, .. } if body == "{\"generation\":")
+        );
+    }
+
+    #[test]
+    fn test_parse_every_model_id() {
+        let ids = [
+            "ai21.j2-mid-v1",
+            "ai21.j2-ultra-v1",
+            "amazon.titan-text-lite-v1",
+            "amazon.titan-embed-text-v1",
+            "amazon.titan-text-express-v1",
+            "amazon.titan-text-agile-v1",
+            "amazon.titan-image-generator-v1",
+            "amazon.titan-embed-text-v2:0",
+            "amazon.titan-embed-image-v1",
+            "anthropic.claude-v1",
+            "anthropic.claude-v2",
+            "anthropic.claude-v2:1",
+            "anthropic.claude-instant-v1",
+            "anthropic.claude-3-haiku-20240307-v1:0",
+            "anthropic.claude-3-sonnet-20240229-v1:0",
+            "anthropic.claude-3-opus-20240229-v1:0",
+            "cohere.command-text-v14",
+            "cohere.command-light-text-v14",
+            "cohere.command-r-v1:0",
+            "cohere.command-r-plus-v1:0",
+            "cohere.embed-english-v3",
+            "cohere.embed-multilingual-v3",
+            "meta.llama2-13b-chat-v1",
+            "meta.llama2-70b-chat-v1",
+            "meta.llama3-8b-instruct-v1:0",
+            "meta.llama3-70b-instruct-v1:0",
+            "mistral.mistral-7b-instruct-v0:2",
+            "mistral.mixtral-8x7b-instruct-v0:1",
+            "mistral.mistral-large-2402-v1:0",
+            "stability.stable-diffusion-xl-v0",
+        ];
+        let models: Vec<BaseModel> = BaseModel::all().collect();
+        assert_eq!(models.len(), ids.len());
+
+        for (model, id) in models.into_iter().zip(ids) {
+            assert_eq!(model.to_string(), id);
+            assert_eq!(id.parse::<BaseModel>().unwrap(), model);
+
+            let json = serde_json::to_string(&model).unwrap();
+            assert_eq!(json, format!("\"{id}\""));
+            assert_eq!(serde_json::from_str::<BaseModel>(&json).unwrap(), model);
+        }
+    }
+
+    #[test]
+    fn test_parse_provider_model() {
+        assert_eq!(
+            "anthropic.claude-v2".parse::<AnthropicModel>().unwrap(),
+            AnthropicModel::ClaudeV2
+        );
+        assert_eq!(
+            "claude-v2".parse::<AnthropicModel>().unwrap(),
+            AnthropicModel::ClaudeV2
+        );
+        assert_eq!(
+            serde_json::from_str::<CohereModel>("\"cohere.embed-english-v3\"").unwrap(),
+            CohereModel::EmbedEnglishV3
+        );
+        assert!("meta.llama2-13b-chat-v1".parse::<AnthropicModel>().is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_model_id() {
+        for id in ["anthropic.claude-v9", "openai.gpt-4", "claude-v2", ""] {
+            let err = id.parse::<BaseModel>().unwrap_err();
+            assert!(matches!(err, StoneMasonError::UnknownModelId(ref s) if s == id));
+        }
+        assert_eq!(
+            "openai.gpt-4".parse::<BaseModel>().unwrap_err().to_string(),
+            "unknown model id \"openai.gpt-4\""
+        );
+
+        let err = serde_json::from_str::<BaseModel>("\"anthropic.claude-v9\"").unwrap_err();
+        assert!(err.to_string().contains("unknown model id"));
+    }
+
+    #[test]
+    fn test_stream_request_body() {
+        let params = cohere::CohereParamsBuilder::default()
+            .prompt("Hi".to_string())
+            .build()
+            .unwrap();
+        let body = |stream_field| {
+            let body = stream_request_body(&params, stream_field).unwrap();
+            serde_json::from_slice::<serde_json::Value>(body.as_ref()).unwrap()
+        };
+
+        assert_eq!(body(false), serde_json::json!({"prompt": "Hi"}));
+        assert_eq!(
+            body(true),
+            serde_json::json!({"prompt": "Hi", "stream": true})
+        );
+        let stream_fields = [
+            <cohere::CohereChatModel as StreamingModel>::STREAM_FIELD,
+            <anthropic::ClaudeTextModel as StreamingModel>::STREAM_FIELD,
+        ];
+        assert_eq!(stream_fields, [true, false]);
+    }
 }
@@ -0,0 +1,66 @@
+/// Declares an enum of those models of a provider which share an API, i.e. take the same
+/// request params and return the same response, so that each API gets its own
+/// [`crate::BedrockModel`]. Variants are named after the provider enum's, which they convert
+/// to and from.
+macro_rules! api_model {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident: $provider:ident, $params:literal {
+            $($variant:ident),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub const ALL: [$name; [$(stringify!($variant)),+].len()] = [$($name::$variant),+];
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Display::fmt(&$provider::from(*self), f)
+            }
+        }
+
+        impl From<$name> for $provider {
+            fn from(model: $name) -> Self {
+                match model {
+                    $($name::$variant => $provider::$variant),+
+                }
+            }
+        }
+
+        impl From<$name> for crate::BaseModel {
+            fn from(model: $name) -> Self {
+                $provider::from(model).into()
+            }
+        }
+
+        /// Fails for the models of the provider which do not take these params.
+        impl TryFrom<$provider> for $name {
+            type Error = crate::StoneMasonError;
+
+            fn try_from(model: $provider) -> crate::Result<Self> {
+                match model {
+                    $($provider::$variant => Ok($name::$variant),)+
+                    _ => Err(crate::StoneMasonError::UnsupportedOperation {
+                        model: model.model_id(),
+                        operation: $params,
+                    }),
+                }
+            }
+        }
+
+        /// Parses a model id, with or without the provider prefix.
+        impl std::str::FromStr for $name {
+            type Err = crate::StoneMasonError;
+
+            fn from_str(s: &str) -> crate::Result<Self> {
+                s.parse::<$provider>()?.try_into()
+            }
+        }
+    };
+}
@@ -1,22 +1,67 @@
-use crate::ModelVersion;
-use crate::ModelVersion::V1;
+use crate::completion::{validate_turns, Conversation, Role};
+use crate::stream::{StreamChunk, StreamingModel};
+use crate::Result;
+use crate::{BedrockModel, FromModelOutput};
 use derive_builder::Builder;
-use serde::Serialize;
-use std::fmt::{Display, Error, Formatter};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MetaModel {
-    Llama2Chat13B(ModelVersion),
+    Llama2Chat13BV1,
+    Llama2Chat70BV1,
+    Llama3Instruct8BV1,
+    Llama3Instruct70BV1,
 }
 
-impl Display for MetaModel {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl MetaModel {
+    pub const ALL: [MetaModel; 4] = [
+        MetaModel::Llama2Chat13BV1,
+        MetaModel::Llama2Chat70BV1,
+        MetaModel::Llama3Instruct8BV1,
+        MetaModel::Llama3Instruct70BV1,
+    ];
+
+    /// The chat template the model was trained on.
+    pub fn chat_template(&self) -> ChatTemplate {
         match self {
-            MetaModel::Llama2Chat13B(v) if *v == V1 => write!(f, "llama2-13b-chat-v1"),
-            _ => Err(Error),
+            MetaModel::Llama2Chat13BV1 | MetaModel::Llama2Chat70BV1 => ChatTemplate::Llama2,
+            MetaModel::Llama3Instruct8BV1 | MetaModel::Llama3Instruct70BV1 => ChatTemplate::Llama3,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTemplate {
+    /// `<s>[INST] <<SYS>>...<</SYS>> ... [/INST]`
+    Llama2,
+
+    /// `<|start_header_id|>user<|end_header_id|> ... <|eot_id|>`
+    Llama3,
+}
+
+impl Display for MetaModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let id = match self {
+            MetaModel::Llama2Chat13BV1 => "llama2-13b-chat-v1",
+            MetaModel::Llama2Chat70BV1 => "llama2-70b-chat-v1",
+            MetaModel::Llama3Instruct8BV1 => "llama3-8b-instruct-v1:0",
+            MetaModel::Llama3Instruct70BV1 => "llama3-70b-instruct-v1:0",
+        };
+        write!(f, "{id}")
+    }
+}
+
+impl BedrockModel for MetaModel {
+    const PROVIDER: &'static str = "meta";
+    type Params = MetaParams;
+    type Response = MetaResponse;
+}
+
+impl StreamingModel for MetaModel {
+    type Chunk = MetaStreamChunk;
+}
+
 #[derive(Serialize, Builder)]
 #[builder(setter(strip_option))]
 pub struct MetaParams {
@@ -34,3 +79,272 @@ pub struct MetaParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_gen_len: Option<i32>,
 }
+
+impl MetaParamsBuilder {
+    /// Sets the prompt to `conversation`, rendered with the chat template of `model`.
+    pub fn conversation_for(
+        &mut self,
+        model: &MetaModel,
+        conversation: &Conversation,
+    ) -> Result<&mut Self> {
+        Ok(self.prompt(render_prompt(model, conversation)?))
+    }
+}
+
+/// Renders `conversation` into the chat template of `model`, checking that the conversation
+/// starts with a user turn and alternates between user and assistant turns. Turn contents are
+/// trimmed, as in Meta's reference implementation, and a trailing assistant turn is left open
+/// for the model to continue from.
+///
+/// Llama 2 wraps the system prompt in `<<SYS>>` tags inside the first `[INST]`:
+///
+/// ```
+/// use stone_mason::completion::Conversation;
+/// use stone_mason::meta::{render_prompt, MetaModel};
+///
+/// let conversation = Conversation::new()
+///     .system("Answer in one sentence.")
+///     .user("What is Amazon Bedrock?");
+///
+/// assert_eq!(
+///     render_prompt(&MetaModel::Llama2Chat13BV1, &conversation).unwrap(),
+///     "<s>[INST] <<SYS>>\nAnswer in one sentence.\n<</SYS>>\n\nWhat is Amazon Bedrock? [/INST]"
+/// );
+/// ```
+///
+/// while Llama 3 gives it a `system` header of its own, and ends the prompt with an open
+/// `assistant` header:
+///
+/// ```
+/// # use stone_mason::completion::Conversation;
+/// # use stone_mason::meta::{render_prompt, MetaModel};
+/// # let conversation = Conversation::new()
+/// #     .system("Answer in one sentence.")
+/// #     .user("What is Amazon Bedrock?");
+/// assert_eq!(
+///     render_prompt(&MetaModel::Llama3Instruct8BV1, &conversation).unwrap(),
+///     "<|begin_of_text|>\
+///      <|start_header_id|>system<|end_header_id|>\n\nAnswer in one sentence.<|eot_id|>\
+///      <|start_header_id|>user<|end_header_id|>\n\nWhat is Amazon Bedrock?<|eot_id|>\
+///      <|start_header_id|>assistant<|end_header_id|>\n\n"
+/// );
+/// ```
+pub fn render_prompt(model: &MetaModel, conversation: &Conversation) -> Result<String> {
+    validate_turns(conversation.messages())?;
+    Ok(match model.chat_template() {
+        ChatTemplate::Llama2 => render_llama2(conversation),
+        ChatTemplate::Llama3 => render_llama3(conversation),
+    })
+}
+
+fn render_llama2(conversation: &Conversation) -> String {
+    let mut prompt = String::new();
+    for (i, message) in conversation.messages().iter().enumerate() {
+        let content = message.content.trim();
+        match message.role {
+            Role::User => {
+                prompt.push_str("<s>[INST] ");
+                if let (0, Some(system)) = (i, conversation.system_prompt()) {
+                    prompt.push_str(&format!("<<SYS>>\n{}\n<</SYS>>\n\n", system.trim()));
+                }
+                prompt.push_str(&format!("{content} [/INST]"));
+            }
+            Role::Assistant if i == conversation.messages().len() - 1 => {
+                prompt.push_str(&format!(" {content}"));
+            }
+            Role::Assistant => prompt.push_str(&format!(" {content} </s>")),
+        }
+    }
+    prompt
+}
+
+fn render_llama3(conversation: &Conversation) -> String {
+    let header = |role: &str| format!("<|start_header_id|>{role}<|end_header_id|>\n\n");
+    let mut prompt = "<|begin_of_text|>".to_string();
+    if let Some(system) = conversation.system_prompt() {
+        prompt.push_str(&format!("{}{}<|eot_id|>", header("system"), system.trim()));
+    }
+    for (i, message) in conversation.messages().iter().enumerate() {
+        let content = message.content.trim();
+        match message.role {
+            Role::User => prompt.push_str(&format!("{}{content}<|eot_id|>", header("user"))),
+            Role::Assistant if i == conversation.messages().len() - 1 => {
+                prompt.push_str(&format!("{}{content}", header("assistant")));
+            }
+            Role::Assistant => {
+                prompt.push_str(&format!("{}{content}<|eot_id|>", header("assistant")));
+            }
+        }
+    }
+    if conversation.messages().last().map(|m| m.role) == Some(Role::User) {
+        prompt.push_str(&header("assistant"));
+    }
+    prompt
+}
+
+/// `stop_reason` is either `stop` or `length`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MetaResponse {
+    pub generation: String,
+    pub prompt_token_count: u32,
+    pub generation_token_count: u32,
+    pub stop_reason: String,
+}
+
+impl<'de> FromModelOutput<'de, MetaResponse> for MetaResponse {}
+
+/// The token counts are only populated on the first and last chunks respectively.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MetaStreamChunk {
+    pub generation: String,
+    pub prompt_token_count: Option<u32>,
+    pub generation_token_count: Option<u32>,
+    pub stop_reason: Option<String>,
+}
+
+impl StreamChunk for MetaStreamChunk {
+    fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_render_single_turn() {
+        let prompt = render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new().user("Hello"),
+        )
+        .unwrap();
+        assert_eq!(prompt, "<s>[INST] Hello [/INST]");
+    }
+
+    #[test]
+    fn test_render_multi_turn_with_system() {
+        let prompt = render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new()
+                .system("You are a helpful, respectful and honest assistant.")
+                .user("There's a llama in my garden 😱 What should I do?")
+                .assistant("Call animal control.")
+                .user("  It ate my lettuce!  "),
+        )
+        .unwrap();
+
+        assert_eq!(
+            prompt,
+            "<s>[INST] <<SYS>>\nYou are a helpful, respectful and honest assistant.\n<</SYS>>\n\n\
+             There's a llama in my garden 😱 What should I do? [/INST] Call animal control. </s>\
+             <s>[INST] It ate my lettuce! [/INST]"
+        );
+    }
+
+    #[test]
+    fn test_render_prefill() {
+        let prompt = render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new()
+                .user("List three colors as JSON")
+                .assistant("["),
+        )
+        .unwrap();
+        assert_eq!(prompt, "<s>[INST] List three colors as JSON [/INST] [");
+    }
+
+    #[test]
+    fn test_render_rejects_invalid_conversations() {
+        assert!(render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new().system("Hi")
+        )
+        .is_err());
+        assert!(render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new().assistant("Hi")
+        )
+        .is_err());
+        assert!(render_prompt(
+            &MetaModel::Llama2Chat13BV1,
+            &Conversation::new()
+                .user("Hi")
+                .assistant("Hello")
+                .assistant("Anyone there?")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_params_from_conversation() {
+        let params = MetaParamsBuilder::default()
+            .conversation_for(
+                &MetaModel::Llama2Chat13BV1,
+                &Conversation::new().system("Be brief.").user("Hello"),
+            )
+            .unwrap()
+            .max_gen_len(128)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"prompt":"<s>[INST] <<SYS>>\nBe brief.\n<</SYS>>\n\nHello [/INST]","max_gen_len":128}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_response() {
+        let output = model_output(include_str!("../tests/fixtures/meta/llama2_chat.json"));
+        let res = MetaResponse::from_model_output(&output).unwrap();
+
+        assert!(res.generation.starts_with(" Sure!"));
+        assert_eq!(res.prompt_token_count, 24);
+        assert_eq!(res.generation_token_count, 61);
+        assert_eq!(res.stop_reason, "stop");
+    }
+
+    #[test]
+    fn test_render_llama3_multi_turn_with_prefill() {
+        let prompt = render_prompt(
+            &MetaModel::Llama3Instruct8BV1,
+            &Conversation::new()
+                .system("Be brief.")
+                .user("Hi")
+                .assistant("Hello!")
+                .user("List three colors as JSON")
+                .assistant("["),
+        )
+        .unwrap();
+
+        assert_eq!(
+            prompt,
+            "<|begin_of_text|>\
+             <|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\nHello!<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nList three colors as JSON<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n["
+        );
+    }
+
+    #[test]
+    fn test_params_for_model() {
+        let conversation = Conversation::new().user("Hello");
+        let params = MetaParamsBuilder::default()
+            .conversation_for(&MetaModel::Llama3Instruct8BV1, &conversation)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(params.prompt.starts_with("<|begin_of_text|>"));
+
+        let params = MetaParamsBuilder::default()
+            .conversation_for(&MetaModel::Llama2Chat70BV1, &conversation)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(params.prompt, "<s>[INST] Hello [/INST]");
+    }
+}
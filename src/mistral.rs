@@ -0,0 +1,193 @@
+use crate::completion::{validate_turns, Conversation, Role};
+use crate::stream::{StreamChunk, StreamingModel};
+use crate::Result;
+use crate::{BedrockModel, FromModelOutput};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MistralModel {
+    Mistral7BInstructV0_2,
+    Mixtral8x7BInstructV0_1,
+    MistralLargeV1,
+}
+
+impl MistralModel {
+    pub const ALL: [MistralModel; 3] = [
+        MistralModel::Mistral7BInstructV0_2,
+        MistralModel::Mixtral8x7BInstructV0_1,
+        MistralModel::MistralLargeV1,
+    ];
+}
+
+impl Display for MistralModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let id = match self {
+            MistralModel::Mistral7BInstructV0_2 => "mistral-7b-instruct-v0:2",
+            MistralModel::Mixtral8x7BInstructV0_1 => "mixtral-8x7b-instruct-v0:1",
+            MistralModel::MistralLargeV1 => "mistral-large-2402-v1:0",
+        };
+        write!(f, "{id}")
+    }
+}
+
+impl BedrockModel for MistralModel {
+    const PROVIDER: &'static str = "mistral";
+    type Params = MistralParams;
+    type Response = MistralResponse;
+}
+
+impl StreamingModel for MistralModel {
+    type Chunk = MistralResponse;
+}
+
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(strip_option))]
+pub struct MistralParams {
+    pub prompt: String,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+}
+
+impl MistralParamsBuilder {
+    /// Sets the prompt to the rendered `conversation`.
+    pub fn conversation(&mut self, conversation: &Conversation) -> Result<&mut Self> {
+        Ok(self.prompt(render_prompt(conversation)?))
+    }
+}
+
+/// Renders `conversation` into the `<s>[INST] ... [/INST]` chat template the Mistral instruct
+/// models were trained on, checking that the conversation starts with a user turn and
+/// alternates between user and assistant turns. The template has no place for a system prompt,
+/// so it is prepended to the first user turn, and a trailing assistant turn is left open for
+/// the model to continue from.
+///
+/// ```
+/// use stone_mason::completion::Conversation;
+/// use stone_mason::mistral::render_prompt;
+///
+/// let prompt = render_prompt(
+///     &Conversation::new()
+///         .system("Answer in one sentence.")
+///         .user("What is Amazon Bedrock?"),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     prompt,
+///     "<s>[INST] Answer in one sentence.\n\nWhat is Amazon Bedrock? [/INST]"
+/// );
+/// ```
+pub fn render_prompt(conversation: &Conversation) -> Result<String> {
+    let messages = conversation.messages();
+    validate_turns(messages)?;
+
+    let mut prompt = "<s>".to_string();
+    for (i, message) in messages.iter().enumerate() {
+        let content = message.content.trim();
+        match message.role {
+            Role::User => match (i, conversation.system_prompt()) {
+                (0, Some(system)) => {
+                    prompt.push_str(&format!("[INST] {}\n\n{content} [/INST]", system.trim()))
+                }
+                _ => prompt.push_str(&format!("[INST] {content} [/INST]")),
+            },
+            Role::Assistant if i == messages.len() - 1 => {
+                prompt.push_str(&format!(" {content}"));
+            }
+            Role::Assistant => prompt.push_str(&format!(" {content}</s>")),
+        }
+    }
+    Ok(prompt)
+}
+
+/// Used both for the response body and for each chunk of a streamed response.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MistralResponse {
+    pub outputs: Vec<MistralOutput>,
+}
+
+impl<'de> FromModelOutput<'de, MistralResponse> for MistralResponse {}
+
+/// `stop_reason` is either `stop` or `length`, and is `None` on all but the last chunk of a
+/// streamed response.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MistralOutput {
+    pub text: String,
+    pub stop_reason: Option<String>,
+}
+
+impl StreamChunk for MistralResponse {
+    fn stop_reason(&self) -> Option<&str> {
+        self.outputs.first()?.stop_reason.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    #[test]
+    fn test_render_multi_turn_with_prefill() {
+        let prompt = render_prompt(
+            &Conversation::new()
+                .system("Be brief.")
+                .user("Hi")
+                .assistant("Hello!")
+                .user("List three colors as JSON")
+                .assistant("["),
+        )
+        .unwrap();
+
+        assert_eq!(
+            prompt,
+            "<s>[INST] Be brief.\n\nHi [/INST] Hello!</s>[INST] List three colors as JSON [/INST] ["
+        );
+    }
+
+    #[test]
+    fn test_params_from_conversation() {
+        let params = MistralParamsBuilder::default()
+            .conversation(&Conversation::new().user("Hello"))
+            .unwrap()
+            .max_tokens(200)
+            .stop(vec!["</s>".to_string()])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"prompt":"<s>[INST] Hello [/INST]","max_tokens":200,"stop":["</s>"]}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_response() {
+        let output = model_output(include_str!("../tests/fixtures/mistral/mistral_7b.json"));
+        let res = MistralResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.outputs.len(), 1);
+        assert!(res.outputs[0].text.starts_with(" Amazon Bedrock"));
+        assert_eq!(res.stop_reason(), Some("stop"));
+    }
+}
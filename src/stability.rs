@@ -1,40 +1,299 @@
-use crate::ModelVersion;
-use crate::ModelVersion::V0;
+use crate::image;
+pub use crate::image::Image;
+use crate::{BedrockModel, FromModelOutput, Result, StoneMasonError};
 use derive_builder::Builder;
-use serde::Serialize;
-use std::fmt::{Display, Error, Formatter};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StabilityAIModel {
-    StableDiffusionXL(ModelVersion),
+    StableDiffusionXLV0,
+}
+
+impl StabilityAIModel {
+    pub const ALL: [StabilityAIModel; 1] = [StabilityAIModel::StableDiffusionXLV0];
 }
 
 impl Display for StabilityAIModel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            StabilityAIModel::StableDiffusionXL(v) if *v == V0 => {
-                write!(f, "stable-diffusion-xl-v0")
-            }
-            _ => Err(Error),
-        }
+        let id = match self {
+            StabilityAIModel::StableDiffusionXLV0 => "stable-diffusion-xl-v0",
+        };
+        write!(f, "{id}")
     }
 }
 
+impl BedrockModel for StabilityAIModel {
+    const PROVIDER: &'static str = "stability";
+    type Params = StabilityParams;
+    type Response = StabilityResponse;
+}
+
 #[derive(Builder, Debug, Clone, Serialize)]
-#[builder(setter(strip_option))]
+#[builder(setter(strip_option), build_fn(validate = "Self::validate"))]
 pub struct StabilityParams {
     text_prompts: Vec<TextPrompt>,
 
+    /// Serialized as the `height` and `width` fields.
+    #[builder(default = "None")]
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    dimensions: Option<Dimensions>,
+
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     cfg_scale: Option<f32>,
 
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clip_guidance_preset: Option<ClipGuidancePreset>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampler: Option<Sampler>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    samples: Option<u32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
     steps: Option<i32>,
 
     #[builder(default = "None")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    seed: Option<i32>,
+    style_preset: Option<StylePreset>,
+
+    /// Image used as the starting point for image-to-image generation.
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init_image: Option<Image>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init_image_mode: Option<InitImageMode>,
+
+    /// How much influence `init_image` has on the result, from 0 to 1. Used with
+    /// [`InitImageMode::ImageStrength`].
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_strength: Option<f32>,
+
+    /// Used with [`InitImageMode::StepSchedule`].
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step_schedule_start: Option<f32>,
+
+    /// Used with [`InitImageMode::StepSchedule`].
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step_schedule_end: Option<f32>,
+
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask_source: Option<MaskSource>,
+
+    /// Required when `mask_source` is [`MaskSource::MaskImageWhite`] or
+    /// [`MaskSource::MaskImageBlack`].
+    #[builder(default = "None")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask_image: Option<Image>,
+}
+
+impl StabilityParamsBuilder {
+    fn validate(&self) -> Result<(), String> {
+        let has_init_image = matches!(self.init_image, Some(Some(_)));
+        let unit_ranges = [
+            ("image_strength", self.image_strength),
+            ("step_schedule_start", self.step_schedule_start),
+            ("step_schedule_end", self.step_schedule_end),
+        ];
+        for (name, value) in unit_ranges {
+            if let Some(Some(value)) = value {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(format!("{name} must be between 0 and 1, got {value}"));
+                }
+                if !has_init_image {
+                    return Err(format!("{name} requires init_image"));
+                }
+            }
+        }
+
+        if matches!(self.init_image_mode, Some(Some(_))) && !has_init_image {
+            return Err("init_image_mode requires init_image".to_string());
+        }
+
+        match (
+            self.mask_source.flatten(),
+            matches!(self.mask_image, Some(Some(_))),
+        ) {
+            (Some(_), _) if !has_init_image => Err("mask_source requires init_image".to_string()),
+            (Some(MaskSource::InitImageAlpha), true) => {
+                Err("mask_image cannot be used with MaskSource::InitImageAlpha".to_string())
+            }
+            (Some(MaskSource::MaskImageWhite | MaskSource::MaskImageBlack), false) => Err(
+                "mask_image is required for MaskSource::MaskImageWhite and MaskImageBlack"
+                    .to_string(),
+            ),
+            (None, true) => Err("mask_image requires mask_source".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The image dimensions SDXL supports, as `W{width}H{height}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimensions {
+    W1024H1024,
+    W1152H896,
+    W1216H832,
+    W1344H768,
+    W1536H640,
+    W640H1536,
+    W768H1344,
+    W832H1216,
+    W896H1152,
+}
+
+impl Dimensions {
+    pub const ALL: [Dimensions; 9] = [
+        Dimensions::W1024H1024,
+        Dimensions::W1152H896,
+        Dimensions::W1216H832,
+        Dimensions::W1344H768,
+        Dimensions::W1536H640,
+        Dimensions::W640H1536,
+        Dimensions::W768H1344,
+        Dimensions::W832H1216,
+        Dimensions::W896H1152,
+    ];
+
+    pub fn width(&self) -> u32 {
+        self.size().0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size().1
+    }
+
+    fn size(&self) -> (u32, u32) {
+        match self {
+            Dimensions::W1024H1024 => (1024, 1024),
+            Dimensions::W1152H896 => (1152, 896),
+            Dimensions::W1216H832 => (1216, 832),
+            Dimensions::W1344H768 => (1344, 768),
+            Dimensions::W1536H640 => (1536, 640),
+            Dimensions::W640H1536 => (640, 1536),
+            Dimensions::W768H1344 => (768, 1344),
+            Dimensions::W832H1216 => (832, 1216),
+            Dimensions::W896H1152 => (896, 1152),
+        }
+    }
+}
+
+impl TryFrom<(u32, u32)> for Dimensions {
+    type Error = StoneMasonError;
+
+    /// Converts a `(width, height)` pair, failing if SDXL does not support it.
+    fn try_from(size: (u32, u32)) -> Result<Self> {
+        Dimensions::ALL
+            .into_iter()
+            .find(|d| d.size() == size)
+            .ok_or_else(|| {
+                StoneMasonError::invalid_params(format!(
+                    "{}x{} is not a supported SDXL size",
+                    size.0, size.1
+                ))
+            })
+    }
+}
+
+impl Serialize for Dimensions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Dimensions", 2)?;
+        state.serialize_field("height", &self.height())?;
+        state.serialize_field("width", &self.width())?;
+        state.end()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClipGuidancePreset {
+    FastBlue,
+    FastGreen,
+    None,
+    Simple,
+    Slow,
+    Slower,
+    Slowest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Sampler {
+    #[serde(rename = "DDIM")]
+    Ddim,
+    #[serde(rename = "DDPM")]
+    Ddpm,
+    #[serde(rename = "K_DPMPP_2M")]
+    KDpmpp2M,
+    #[serde(rename = "K_DPMPP_2S_ANCESTRAL")]
+    KDpmpp2SAncestral,
+    #[serde(rename = "K_DPM_2")]
+    KDpm2,
+    #[serde(rename = "K_DPM_2_ANCESTRAL")]
+    KDpm2Ancestral,
+    #[serde(rename = "K_EULER")]
+    KEuler,
+    #[serde(rename = "K_EULER_ANCESTRAL")]
+    KEulerAncestral,
+    #[serde(rename = "K_HEUN")]
+    KHeun,
+    #[serde(rename = "K_LMS")]
+    KLms,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StylePreset {
+    #[serde(rename = "3d-model")]
+    ThreeDModel,
+    AnalogFilm,
+    Anime,
+    Cinematic,
+    ComicBook,
+    DigitalArt,
+    Enhance,
+    FantasyArt,
+    Isometric,
+    LineArt,
+    LowPoly,
+    ModelingCompound,
+    NeonPunk,
+    Origami,
+    Photographic,
+    PixelArt,
+    TileTexture,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InitImageMode {
+    ImageStrength,
+    StepSchedule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaskSource {
+    MaskImageWhite,
+    MaskImageBlack,
+    InitImageAlpha,
 }
 
 #[derive(Builder, Debug, Clone, Serialize)]
@@ -42,3 +301,193 @@ pub struct TextPrompt {
     text: String,
     weight: Option<f32>,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StabilityResponse {
+    pub result: String,
+    pub artifacts: Vec<Artifact>,
+}
+
+impl<'de> FromModelOutput<'de, StabilityResponse> for StabilityResponse {}
+
+impl StabilityResponse {
+    /// Writes each artifact into `dir` as `{index}_{seed}.png`, returning the paths written.
+    pub fn save_artifacts(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let images = self.artifacts.iter().enumerate().map(|(i, artifact)| {
+            (
+                format!("{i}_{}.png", artifact.seed),
+                artifact.image.as_bytes(),
+            )
+        });
+        image::save_all(dir.as_ref(), images)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Artifact {
+    /// The generated PNG image, decoded from base64.
+    #[serde(rename = "base64")]
+    pub image: Image,
+
+    pub seed: u32,
+
+    #[serde(rename = "finishReason")]
+    pub finish_reason: FinishReason,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FinishReason {
+    Success,
+    Error,
+    ContentFiltered,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::model_output;
+
+    const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn prompts() -> Vec<TextPrompt> {
+        vec![TextPromptBuilder::default()
+            .text("a lighthouse at dusk".to_string())
+            .weight(Some(1.0))
+            .build()
+            .unwrap()]
+    }
+
+    #[test]
+    fn test_serialize_text_to_image_params() {
+        let params = StabilityParamsBuilder::default()
+            .text_prompts(prompts())
+            .dimensions(Dimensions::W1344H768)
+            .sampler(Sampler::KDpmpp2SAncestral)
+            .style_preset(StylePreset::ThreeDModel)
+            .clip_guidance_preset(ClipGuidancePreset::FastBlue)
+            .samples(1)
+            .seed(u32::MAX)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "text_prompts": [{"text": "a lighthouse at dusk", "weight": 1.0}],
+                "height": 768,
+                "width": 1344,
+                "clip_guidance_preset": "FAST_BLUE",
+                "sampler": "K_DPMPP_2S_ANCESTRAL",
+                "samples": 1,
+                "seed": 4294967295u32,
+                "style_preset": "3d-model",
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_image_to_image_params() {
+        let params = StabilityParamsBuilder::default()
+            .text_prompts(prompts())
+            .init_image(Image(b"init".to_vec()))
+            .init_image_mode(InitImageMode::ImageStrength)
+            .image_strength(0.35)
+            .mask_source(MaskSource::MaskImageBlack)
+            .mask_image(Image(b"mask".to_vec()))
+            .build()
+            .unwrap();
+
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["init_image"], "aW5pdA==");
+        assert_eq!(value["init_image_mode"], "IMAGE_STRENGTH");
+        assert_eq!(value["mask_source"], "MASK_IMAGE_BLACK");
+        assert_eq!(value["mask_image"], "bWFzaw==");
+    }
+
+    #[test]
+    fn test_dimensions_from_size() {
+        assert_eq!(
+            Dimensions::try_from((896, 1152)).unwrap(),
+            Dimensions::W896H1152
+        );
+        assert!(Dimensions::try_from((512, 512)).is_err());
+    }
+
+    #[test]
+    fn test_validate_image_to_image_params() {
+        let err = StabilityParamsBuilder::default()
+            .text_prompts(prompts())
+            .image_strength(0.5)
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "image_strength requires init_image");
+
+        let err = StabilityParamsBuilder::default()
+            .text_prompts(prompts())
+            .init_image(Image(b"init".to_vec()))
+            .image_strength(1.5)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "image_strength must be between 0 and 1, got 1.5"
+        );
+
+        let err = StabilityParamsBuilder::default()
+            .text_prompts(prompts())
+            .init_image(Image(b"init".to_vec()))
+            .mask_source(MaskSource::MaskImageWhite)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mask_image is required for MaskSource::MaskImageWhite and MaskImageBlack"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_response() {
+        let output = model_output(include_str!("../tests/fixtures/stability/sdxl.json"));
+        let res = StabilityResponse::from_model_output(&output).unwrap();
+
+        assert_eq!(res.result, "success");
+        assert_eq!(res.artifacts.len(), 2);
+
+        let artifact = &res.artifacts[0];
+        assert_eq!(artifact.seed, 1234);
+        assert_eq!(artifact.finish_reason, FinishReason::Success);
+        assert!(artifact.image.as_bytes().starts_with(PNG_MAGIC));
+
+        assert_eq!(
+            res.artifacts[1].finish_reason,
+            FinishReason::ContentFiltered
+        );
+    }
+
+    #[test]
+    fn test_save_artifacts() {
+        let output = model_output(include_str!("../tests/fixtures/stability/sdxl.json"));
+        let res = StabilityResponse::from_model_output(&output).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("stone-mason-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let paths = res.save_artifacts(&dir).unwrap();
+        assert_eq!(paths, vec![dir.join("0_1234.png"), dir.join("1_5678.png")]);
+        assert_eq!(
+            std::fs::read(&paths[0]).unwrap(),
+            res.artifacts[0].image.as_bytes()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_base64_is_rejected() {
+        let output = model_output(
+            r#"{"result":"success","artifacts":[{"seed":1,"base64":"not base64!","finishReason":"SUCCESS"}]}"#,
+        );
+        assert!(StabilityResponse::from_model_output(&output).is_err());
+    }
+}
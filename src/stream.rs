@@ -0,0 +1,154 @@
+use crate::{BedrockModel, Result, StoneMasonError};
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// A model which can be invoked with `InvokeModelWithResponseStream`, decoding each payload
+/// part of the response into [`Self::Chunk`].
+pub trait StreamingModel: BedrockModel {
+    type Chunk: StreamChunk;
+
+    /// Whether the request body must also set `"stream": true`, which
+    /// [`crate::StoneMason::invoke_stream`] then adds to the serialized params.
+    const STREAM_FIELD: bool = false;
+}
+
+/// A single decoded payload part of a streamed response.
+pub trait StreamChunk: DeserializeOwned + Send + 'static {
+    /// The reason generation stopped, only present on the last chunk.
+    fn stop_reason(&self) -> Option<&str>;
+}
+
+/// An item yielded by a response stream.
+#[derive(Debug, Clone)]
+pub enum StreamEvent<C> {
+    /// A chunk of the response, as it arrives.
+    Chunk(C),
+
+    /// Always the last item of a stream which completed without error.
+    End(StreamEnd),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamEnd {
+    pub stop_reason: Option<String>,
+
+    /// Bedrock attaches these to the final chunk of every stream.
+    pub invocation_metrics: Option<InvocationMetrics>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InvocationMetrics {
+    pub input_token_count: u32,
+    pub output_token_count: u32,
+
+    /// Milliseconds.
+    pub invocation_latency: u64,
+
+    /// Milliseconds.
+    pub first_byte_latency: u64,
+}
+
+#[derive(Deserialize)]
+struct MetricsEnvelope {
+    #[serde(rename = "amazon-bedrock-invocationMetrics")]
+    invocation_metrics: Option<InvocationMetrics>,
+}
+
+/// Decodes a stream of raw payload parts into [`StreamEvent`]s, following the last chunk with a
+/// [`StreamEvent::End`]. The stream ends after the first error.
+pub(crate) fn decode_stream<C, S>(parts: S) -> impl Stream<Item = Result<StreamEvent<C>>> + Send
+where
+    C: StreamChunk,
+    S: Stream<Item = Result<Vec<u8>>> + Send + Unpin,
+{
+    futures::stream::unfold(Some((parts, StreamEnd::default())), |state| async move {
+        let (mut parts, mut end) = state?;
+        match parts.next().await {
+            Some(Ok(bytes)) => match decode_chunk::<C>(&bytes, &mut end) {
+                Ok(chunk) => Some((Ok(StreamEvent::Chunk(chunk)), Some((parts, end)))),
+                Err(e) => Some((Err(e), None)),
+            },
+            Some(Err(e)) => Some((Err(e), None)),
+            None => Some((Ok(StreamEvent::End(end)), None)),
+        }
+    })
+}
+
+fn decode_chunk<C: StreamChunk>(bytes: &[u8], end: &mut StreamEnd) -> Result<C> {
+    let chunk: C =
+        serde_json::from_slice(bytes).map_err(|e| StoneMasonError::deserialization(e, bytes))?;
+
+    if let Some(stop_reason) = chunk.stop_reason() {
+        end.stop_reason = Some(stop_reason.to_string());
+    }
+    if let Ok(MetricsEnvelope {
+        invocation_metrics: Some(metrics),
+    }) = serde_json::from_slice(bytes)
+    {
+        end.invocation_metrics = Some(metrics);
+    }
+
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::AnthropicStreamChunk;
+    use futures::TryStreamExt;
+
+    fn parts(chunks: &[&str]) -> impl Stream<Item = Result<Vec<u8>>> + Send + Unpin {
+        let parts: Vec<Result<Vec<u8>>> =
+            chunks.iter().map(|c| Ok(c.as_bytes().to_vec())).collect();
+        futures::stream::iter(parts)
+    }
+
+    #[tokio::test]
+    async fn test_decode_stream_ends_with_stop_reason_and_metrics() {
+        let events: Vec<StreamEvent<AnthropicStreamChunk>> = decode_stream(parts(&[
+            r#"{"completion":" Hello","stop_reason":null,"stop":null}"#,
+            r#"{"completion":" world","stop_reason":"stop_sequence","stop":"\n\nHuman:","amazon-bedrock-invocationMetrics":{"inputTokenCount":12,"outputTokenCount":3,"invocationLatency":640,"firstByteLatency":310}}"#,
+        ]))
+        .try_collect()
+        .await
+        .unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], StreamEvent::Chunk(c) if c.completion == " Hello"));
+        assert!(matches!(&events[1], StreamEvent::Chunk(c) if c.completion == " world"));
+
+        let StreamEvent::End(end) = &events[2] else {
+            panic!("expected the stream to end with StreamEvent::End");
+        };
+        assert_eq!(end.stop_reason.as_deref(), Some("stop_sequence"));
+        assert_eq!(
+            end.invocation_metrics,
+            Some(InvocationMetrics {
+                input_token_count: 12,
+                output_token_count: 3,
+                invocation_latency: 640,
+                first_byte_latency: 310,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_stream_stops_after_error() {
+        let events: Vec<Result<StreamEvent<AnthropicStreamChunk>>> = decode_stream(parts(&[
+            r#"{"completion":" Hello"}"#,
+            "not json",
+            r#"{"completion":"!"}"#,
+        ]))
+        .collect()
+        .await;
+
+        assert_eq!(events.len(), 2);
+        assert!(events[0].is_ok());
+        assert!(matches!(
+            events[1],
+            Err(StoneMasonError::Deserialization { ref body, .. }) if body == "not json"
+        ));
+    }
+}